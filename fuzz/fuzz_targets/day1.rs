@@ -0,0 +1,11 @@
+#![no_main]
+
+use aoc2020::day1::Day1;
+use aoc2020::solution::Solution;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(content) = std::str::from_utf8(data) {
+        let _ = Day1::parse(content);
+    }
+});
@@ -0,0 +1,330 @@
+use crate::common::*;
+use crate::solution::{self, DynSolution};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const DEFAULT_ANSWERS_PATH: &str = "answers.toml";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AnswerKey {
+    pub day: u32,
+    pub input: String,
+    pub part: u32,
+}
+
+/// Known puzzle answers, read from a small TOML subset:
+///
+/// ```toml
+/// [day1.input]      # inputs/day1/input.txt
+/// part1 = "514579"
+/// part2 = 241861950
+/// ```
+///
+/// A bare `[dayN]` section stands for the `input` file of that day.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    expected: BTreeMap<AnswerKey, String>,
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+fn parse_section(line: &str) -> AdventResult<(u32, String)> {
+    let (header, rest) = line
+        .strip_prefix('[')
+        .and_then(|line| line.split_once(']'))
+        .ok_or_else(|| AdventError::parse("Unterminated section", line))?;
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(AdventError::parse_at(
+            "Unexpected text after section",
+            line,
+            rest,
+        ));
+    }
+
+    let header = header.trim();
+    let (day_part, input) = match header.split_once('.') {
+        Some((day_part, input)) => (day_part, unquote(input.trim())),
+        None => (header, solution::DEFAULT_INPUT),
+    };
+
+    let day = day_part
+        .strip_prefix("day")
+        .ok_or_else(|| AdventError::parse_at("Expected a [dayN] section", line, day_part))?;
+    let day = parse_token(line, day)?;
+
+    if input.is_empty() {
+        return Err(AdventError::parse_at("Empty input name", line, header));
+    }
+    Ok((day, input.to_owned()))
+}
+
+fn parse_entry(line: &str) -> AdventResult<(u32, String)> {
+    let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| AdventError::parse("Expected 'partN = <answer>'", line))?;
+
+    let key = key.trim();
+    let part = match key {
+        "part1" => 1,
+        "part2" => 2,
+        _ => return Err(AdventError::parse_at("Unknown part", line, key)),
+    };
+
+    let value = value.trim();
+    let value = if value.starts_with('"') {
+        value
+            .get(1..)
+            .and_then(|rest| rest.find('"').map(|end| &rest[..end]))
+            .ok_or_else(|| AdventError::parse_at("Unterminated string", line, value))?
+    } else {
+        value.split('#').next().unwrap_or_default().trim()
+    };
+
+    if value.is_empty() {
+        return Err(AdventError::parse("Missing answer", line));
+    }
+    Ok((part, value.to_owned()))
+}
+
+impl Answers {
+    pub fn parse(content: &str) -> AdventResult<Self> {
+        let mut answers = Answers::default();
+        let mut section = None;
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                section = Some(parse_section(line).map_err(|err| err.at_line(index + 1))?);
+                continue;
+            }
+
+            let (day, input) = section.clone().ok_or_else(|| {
+                AdventError::parse("Answer outside of a [dayN] section", line).at_line(index + 1)
+            })?;
+            let (part, value) = parse_entry(line).map_err(|err| err.at_line(index + 1))?;
+            answers
+                .expected
+                .insert(AnswerKey { day, input, part }, value);
+        }
+
+        Ok(answers)
+    }
+
+    pub fn read(path: &str) -> AdventResult<Self> {
+        read_and_parse(path, Answers::parse)
+    }
+
+    pub fn get(&self, day: u32, input: &str, part: u32) -> Option<&str> {
+        let key = AnswerKey {
+            day,
+            input: input.to_owned(),
+            part,
+        };
+        self.expected.get(&key).map(String::as_str)
+    }
+
+    /// Names of the inputs with a known answer for `day`, in order.
+    pub fn inputs(&self, day: u32) -> Vec<&str> {
+        let mut inputs: Vec<&str> = self
+            .expected
+            .keys()
+            .filter(|key| key.day == day)
+            .map(|key| key.input.as_str())
+            .collect();
+        inputs.dedup();
+        inputs
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail { expected: String, actual: String },
+    Missing { actual: String },
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub day: u32,
+    pub input: String,
+    pub part: u32,
+    pub verdict: Verdict,
+}
+
+fn judge(expected: Option<&str>, actual: AdventResult<String>) -> Verdict {
+    let actual = match actual {
+        Ok(actual) => actual,
+        Err(AdventError::NoSolution) => AdventError::NoSolution.to_string(),
+        Err(err) => return Verdict::Error(err.to_string()),
+    };
+
+    match expected {
+        Some(expected) if expected == actual => Verdict::Pass,
+        Some(expected) => Verdict::Fail {
+            expected: expected.to_owned(),
+            actual,
+        },
+        None => Verdict::Missing { actual },
+    }
+}
+
+/// Runs every solution on each of its inputs listed in `answers`, or on its
+/// default input when none is listed, and compares both parts. Each input
+/// gets its own `budget`.
+pub fn verify(
+    answers: &Answers,
+    solutions: &[&dyn DynSolution],
+    budget: Option<Duration>,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    for solution in solutions {
+        let day = solution.day();
+        let mut inputs = answers.inputs(day);
+        if inputs.is_empty() {
+            inputs.push(solution::DEFAULT_INPUT);
+        }
+
+        for input in inputs {
+            let path = solution::input_path(day, input);
+            let verdicts = with_budget(budget, || {
+                let parsed = read_source(&path).and_then(|content| {
+                    solution
+                        .parse_input(&content)
+                        .map_err(|err| err.in_file(&path))
+                });
+                match parsed {
+                    Ok(parsed) => vec![
+                        judge(
+                            answers.get(day, input, 1),
+                            solution.solve_part1(parsed.as_ref()),
+                        ),
+                        judge(
+                            answers.get(day, input, 2),
+                            check_deadline().and_then(|_| solution.solve_part2(parsed.as_ref())),
+                        ),
+                    ],
+                    Err(err) => vec![Verdict::Error(err.to_string()); 2],
+                }
+            });
+
+            for (part, verdict) in (1..).zip(verdicts) {
+                checks.push(Check {
+                    day,
+                    input: input.to_owned(),
+                    part,
+                    verdict,
+                });
+            }
+        }
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day1::Day1;
+
+    const ANSWERS: &str = "\
+# Known answers
+[day1]  # main input
+part1 = 514579
+part2 = \"241861950\"  # quoted
+
+[day1.example]
+part1 = 1
+[day3.\"big\"]
+part2 = \"a # b\"
+";
+
+    #[test]
+    fn sections_and_comments() {
+        let answers = Answers::parse(ANSWERS).unwrap();
+        assert_eq!(answers.get(1, "input", 1), Some("514579"));
+        assert_eq!(answers.get(1, "input", 2), Some("241861950"));
+        assert_eq!(answers.get(1, "example", 1), Some("1"));
+        assert_eq!(answers.get(1, "example", 2), None);
+        assert_eq!(answers.get(3, "big", 2), Some("a # b"));
+        assert_eq!(answers.inputs(1), vec!["example", "input"]);
+        assert!(answers.inputs(2).is_empty());
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let err = expect_parse_error(Answers::parse("part1 = 1\n"));
+        assert_eq!(err.line, Some(1));
+
+        let err = expect_parse_error(Answers::parse("[day1]\npart3 = 1\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(1)));
+
+        let err = expect_parse_error(Answers::parse("[day1]\npart1 = # none\n"));
+        assert_eq!(err.line, Some(2));
+
+        let err = expect_parse_error(Answers::parse("[day1]\npart1 = \"1\n"));
+        assert_eq!(err.line, Some(2));
+
+        let err = expect_parse_error(Answers::parse("[dayx]\n"));
+        assert_eq!((err.line, err.column), (Some(1), Some(5)));
+
+        let err = expect_parse_error(Answers::parse("\n[day1] part1 = 1\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(8)));
+
+        let err = expect_parse_error(Answers::parse("[day1\n"));
+        assert_eq!(err.line, Some(1));
+
+        let err = expect_parse_error(Answers::parse("[day1.]\n"));
+        assert_eq!(err.line, Some(1));
+    }
+
+    #[test]
+    fn verdicts() {
+        assert_eq!(judge(Some("12"), Ok("12".to_owned())), Verdict::Pass);
+        assert_eq!(
+            judge(Some("12"), Ok("13".to_owned())),
+            Verdict::Fail {
+                expected: "12".to_owned(),
+                actual: "13".to_owned(),
+            }
+        );
+        assert_eq!(
+            judge(None, Ok("13".to_owned())),
+            Verdict::Missing {
+                actual: "13".to_owned()
+            }
+        );
+        assert_eq!(
+            judge(Some("no solution found"), Err(AdventError::NoSolution)),
+            Verdict::Pass
+        );
+        assert_eq!(
+            judge(Some("12"), Err(AdventError::Solver("stuck".to_owned()))),
+            Verdict::Error("solver failed: stuck".to_owned())
+        );
+    }
+
+    #[test]
+    fn verify_unreadable_input() {
+        let answers = Answers::parse("[day1.missing-input]\npart1 = 1\n").unwrap();
+        let checks = verify(&answers, &[&Day1], None);
+        assert_eq!(checks.len(), 2);
+        for (part, check) in (1..).zip(&checks) {
+            assert_eq!(
+                (check.day, check.input.as_str(), check.part),
+                (1, "missing-input", part)
+            );
+            assert!(matches!(check.verdict, Verdict::Error(_)));
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::common::*;
+use crate::solution::DynSolution;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Parse,
+    Part1,
+    Part2,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Parse, Phase::Part1, Phase::Part2];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Parse => "parse",
+            Phase::Part1 => "part1",
+            Phase::Part2 => "part2",
+        }
+    }
+}
+
+/// Sorted samples of one phase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timings {
+    samples: Vec<Duration>,
+}
+
+impl Timings {
+    pub fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort();
+        Timings { samples }
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples.first().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.last().copied().unwrap_or_default()
+    }
+
+    pub fn median(&self) -> Duration {
+        let len = self.samples.len();
+        match len {
+            0 => Duration::default(),
+            _ if len % 2 == 1 => self.samples[len / 2],
+            _ => (self.samples[len / 2 - 1] + self.samples[len / 2]) / 2,
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.samples.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DayBench {
+    pub day: u32,
+    pub iterations: usize,
+    pub parse: Timings,
+    pub part1: Timings,
+    pub part2: Timings,
+}
+
+impl DayBench {
+    pub fn phase(&self, phase: Phase) -> &Timings {
+        match phase {
+            Phase::Parse => &self.parse,
+            Phase::Part1 => &self.part1,
+            Phase::Part2 => &self.part2,
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.parse.total() + self.part1.total() + self.part2.total()
+    }
+}
+
+fn time<T>(samples: &mut Vec<Duration>, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let res = f();
+    samples.push(start.elapsed());
+    res
+}
+
+/// Times parsing and both parts of `solution` separately, `iterations` times.
+/// The input at `path` is read once, before timing starts.
+///
+/// The answers are discarded: a part without a solution is timed like any other.
+pub fn bench_solution(
+    solution: &dyn DynSolution,
+    path: &str,
+    iterations: usize,
+) -> AdventResult<DayBench> {
+    let content = read_source(path)?;
+    let mut parse = Vec::with_capacity(iterations);
+    let mut part1 = Vec::with_capacity(iterations);
+    let mut part2 = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let input = time(&mut parse, || solution.parse_input(&content))
+            .map_err(|err| err.in_file(source_name(path)))?;
+        black_box(time(&mut part1, || solution.solve_part1(input.as_ref())).ok());
+        black_box(time(&mut part2, || solution.solve_part2(input.as_ref())).ok());
+    }
+
+    Ok(DayBench {
+        day: solution.day(),
+        iterations,
+        parse: Timings::new(parse),
+        part1: Timings::new(part1),
+        part2: Timings::new(part2),
+    })
+}
+
+pub fn to_csv(results: &[DayBench]) -> String {
+    let mut out = String::from("day,phase,iterations,min_ns,median_ns,max_ns,total_ns\n");
+    for result in results {
+        for &phase in &Phase::ALL {
+            let timings = result.phase(phase);
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                result.day,
+                phase.name(),
+                result.iterations,
+                timings.min().as_nanos(),
+                timings.median().as_nanos(),
+                timings.max().as_nanos(),
+                timings.total().as_nanos()
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+pub fn to_json(results: &[DayBench]) -> String {
+    let days: Vec<String> = results
+        .iter()
+        .map(|result| {
+            let phases: Vec<String> = Phase::ALL
+                .iter()
+                .map(|&phase| {
+                    let timings = result.phase(phase);
+                    format!(
+                        "\"{}\":{{\"min_ns\":{},\"median_ns\":{},\"max_ns\":{},\"total_ns\":{}}}",
+                        phase.name(),
+                        timings.min().as_nanos(),
+                        timings.median().as_nanos(),
+                        timings.max().as_nanos(),
+                        timings.total().as_nanos()
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"day\":{},\"iterations\":{},{},\"total_ns\":{}}}",
+                result.day,
+                result.iterations,
+                phases.join(","),
+                result.total().as_nanos()
+            )
+        })
+        .collect();
+    format!("[{}]\n", days.join(","))
+}
+
+pub fn to_table(results: &[DayBench]) -> String {
+    let mut out = format!(
+        "{:>5} {:>6} {:>12} {:>12} {:>12} {:>12}\n",
+        "day", "phase", "min", "median", "max", "total"
+    );
+    for result in results {
+        for &phase in &Phase::ALL {
+            let timings = result.phase(phase);
+            writeln!(
+                out,
+                "{:>5} {:>6} {:>12.1?} {:>12.1?} {:>12.1?} {:>12.1?}",
+                result.day,
+                phase.name(),
+                timings.min(),
+                timings.median(),
+                timings.max(),
+                timings.total()
+            )
+            .unwrap();
+        }
+    }
+    let total: Duration = results.iter().map(DayBench::total).sum();
+    writeln!(out, "total: {:.1?}", total).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nanos(samples: &[u64]) -> Timings {
+        Timings::new(samples.iter().copied().map(Duration::from_nanos).collect())
+    }
+
+    fn bench() -> DayBench {
+        DayBench {
+            day: 3,
+            iterations: 2,
+            parse: nanos(&[10, 30]),
+            part1: nanos(&[5, 7]),
+            part2: nanos(&[]),
+        }
+    }
+
+    #[test]
+    fn median() {
+        assert_eq!(nanos(&[9, 1, 5]).median(), Duration::from_nanos(5));
+        assert_eq!(nanos(&[8, 2, 4, 100]).median(), Duration::from_nanos(6));
+        assert_eq!(nanos(&[]).median(), Duration::default());
+
+        let timings = nanos(&[9, 1, 5]);
+        assert_eq!(timings.min(), Duration::from_nanos(1));
+        assert_eq!(timings.max(), Duration::from_nanos(9));
+        assert_eq!(timings.total(), Duration::from_nanos(15));
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            to_csv(&[bench()]),
+            "\
+day,phase,iterations,min_ns,median_ns,max_ns,total_ns
+3,parse,2,10,20,30,40
+3,part1,2,5,6,7,12
+3,part2,2,0,0,0,0
+"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(to_json(&[]), "[]\n");
+        assert_eq!(
+            to_json(&[bench()]),
+            concat!(
+                "[{\"day\":3,\"iterations\":2,",
+                "\"parse\":{\"min_ns\":10,\"median_ns\":20,\"max_ns\":30,\"total_ns\":40},",
+                "\"part1\":{\"min_ns\":5,\"median_ns\":6,\"max_ns\":7,\"total_ns\":12},",
+                "\"part2\":{\"min_ns\":0,\"median_ns\":0,\"max_ns\":0,\"total_ns\":0},",
+                "\"total_ns\":52}]\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,569 @@
+use aoc2020::answers::DEFAULT_ANSWERS_PATH;
+use aoc2020::common::*;
+use aoc2020::day2;
+use aoc2020::history::DEFAULT_HISTORY_PATH;
+use aoc2020::solution::{self, DEFAULT_GENERATE_SIZE, DEFAULT_INPUT};
+use std::time::Duration;
+
+pub const USAGE: &str = "usage:
+    aoc2020 run --day <N> [--part <1|2>] [--input <path|->] [--format <text|json>]
+                [--timeout <seconds>]
+    aoc2020 run --day 1 [--k <N>] [--target <N>] [--list | --count] [--input <path|->]
+                [--format <text|json>]
+    aoc2020 run --day 2 --policy <name> [--policy <name>...] [--input <path|->]
+                [--format <text|json>]
+    aoc2020 run --day 2 --audit [--policy <name>...] [--input <path|->]
+                [--format <text|csv|json>]
+    aoc2020 run --all [--part <1|2>] [--format <text|json>] [--timeout <seconds>]
+                [--jobs <N>]
+    aoc2020 verify [--day <N>] [--answers <path>] [--timeout <seconds>]
+    aoc2020 bench [--day <N>] [--iterations <N>] [--format <text|csv|json>] [--output <path>]
+                  [--save] [--history <path>]
+    aoc2020 bench compare [--baseline <commit>] [--current <commit>] [--threshold <percent>]
+                          [--history <path>]
+    aoc2020 generate --day <N> [--seed <N>] [--size <N>] [--output <path>]
+    aoc2020 new <N>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaySelection {
+    All,
+    Single(u32),
+}
+
+/// What `run --day 1` reports about the entries summing to the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpenseMode {
+    /// The product of the first solution, or the closest sum without one.
+    First,
+    List,
+    Count,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOptions {
+    pub days: DaySelection,
+    pub part: PartSelection,
+    pub input: Option<String>,
+    pub format: OutputFormat,
+    /// Time budget of each day, parsing included.
+    pub timeout: Option<Duration>,
+    /// Worker threads for `--all`; the days run one after another when `None`.
+    pub jobs: Option<usize>,
+    /// Day 1 only: how many entries must add up to `target`.
+    pub k: Option<usize>,
+    pub target: Option<i64>,
+    pub expense_mode: ExpenseMode,
+    /// Day 2 only: password policies to count the valid lines of.
+    pub policies: Vec<String>,
+    /// Day 2 only: report every line against each policy instead of counts.
+    pub audit: bool,
+}
+
+impl RunOptions {
+    /// Whether day 1 runs with options of its own instead of as a solution.
+    pub fn expense_query(&self) -> bool {
+        self.k.is_some() || self.target.is_some() || self.expense_mode != ExpenseMode::First
+    }
+
+    pub fn input_path(&self, day: u32) -> String {
+        match &self.input {
+            Some(path) => path.clone(),
+            None => default_input_path(day),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyOptions {
+    pub days: DaySelection,
+    pub answers: String,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchOptions {
+    pub days: DaySelection,
+    pub iterations: usize,
+    pub format: OutputFormat,
+    pub output: Option<String>,
+    pub save: bool,
+    pub history: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareOptions {
+    pub history: String,
+    pub baseline: Option<String>,
+    pub current: Option<String>,
+    pub threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerateOptions {
+    pub day: u32,
+    pub seed: u64,
+    pub size: usize,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run(RunOptions),
+    Verify(VerifyOptions),
+    Bench(BenchOptions),
+    BenchCompare(CompareOptions),
+    Generate(GenerateOptions),
+    New(u32),
+    Help,
+}
+
+pub fn default_input_path(day: u32) -> String {
+    solution::input_path(day, DEFAULT_INPUT)
+}
+
+fn next_value<'a>(
+    flag: &str,
+    args: &mut impl Iterator<Item = &'a String>,
+) -> Result<&'a String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+fn parse_day(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("Invalid day: {}", value))
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("Invalid timeout: {}", value)),
+    }
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, String> {
+    let mut all = false;
+    let mut day = None;
+    let mut part = PartSelection::Both;
+    let mut input = None;
+    let mut format = OutputFormat::Text;
+    let mut timeout = None;
+    let mut jobs = None;
+    let mut k = None;
+    let mut target = None;
+    let mut expense_mode = ExpenseMode::First;
+    let mut policies = Vec::new();
+    let mut audit = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--day" => {
+                day = Some(parse_day(next_value(arg, &mut args)?)?);
+            }
+            "--part" => {
+                part = match next_value(arg, &mut args)?.as_str() {
+                    "1" => PartSelection::Part1,
+                    "2" => PartSelection::Part2,
+                    other => return Err(format!("Invalid part: {}", other)),
+                };
+            }
+            "--input" => input = Some(next_value(arg, &mut args)?.clone()),
+            "--format" => format = parse_format(next_value(arg, &mut args)?)?,
+            "--timeout" => timeout = Some(parse_timeout(next_value(arg, &mut args)?)?),
+            "--jobs" => {
+                let value = next_value(arg, &mut args)?;
+                jobs = match value.parse() {
+                    Ok(jobs) if jobs > 0 => Some(jobs),
+                    _ => return Err(format!("Invalid job count: {}", value)),
+                };
+            }
+            "--k" => {
+                let value = next_value(arg, &mut args)?;
+                k = match value.parse() {
+                    Ok(k) if k > 0 => Some(k),
+                    _ => return Err(format!("Invalid entry count: {}", value)),
+                };
+            }
+            "--target" => {
+                let value = next_value(arg, &mut args)?;
+                target = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid target: {}", value))?,
+                );
+            }
+            "--list" | "--count" if expense_mode != ExpenseMode::First => {
+                return Err("--list and --count are mutually exclusive".to_owned());
+            }
+            "--policy" => {
+                let spec = next_value(arg, &mut args)?;
+                day2::policy_by_name(spec).map_err(|err| err.to_string())?;
+                policies.push(spec.clone());
+            }
+            "--audit" => audit = true,
+            "--list" => expense_mode = ExpenseMode::List,
+            "--count" => expense_mode = ExpenseMode::Count,
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    let days = match (all, day) {
+        (true, None) => DaySelection::All,
+        (false, Some(day)) => DaySelection::Single(day),
+        (true, Some(_)) => return Err("--all and --day are mutually exclusive".to_owned()),
+        (false, None) => return Err("Either --all or --day must be provided".to_owned()),
+    };
+
+    let options = RunOptions {
+        days,
+        part,
+        input,
+        format,
+        timeout,
+        jobs,
+        k,
+        target,
+        expense_mode,
+        policies,
+        audit,
+    };
+    let day2_query = !options.policies.is_empty() || options.audit;
+
+    if days == DaySelection::All && options.input.is_some() {
+        return Err("--input cannot be used with --all".to_owned());
+    }
+    if days != DaySelection::All && options.jobs.is_some() {
+        return Err("--jobs can only be used with --all".to_owned());
+    }
+    if options.expense_query() && days != DaySelection::Single(1) {
+        return Err("--k, --target, --list and --count can only be used with --day 1".to_owned());
+    }
+    if day2_query && days != DaySelection::Single(2) {
+        return Err("--policy and --audit can only be used with --day 2".to_owned());
+    }
+    if options.format == OutputFormat::Csv && !options.audit {
+        return Err("run only supports csv output with --audit".to_owned());
+    }
+    if options.expense_query() && options.timeout.is_some() {
+        return Err("--timeout cannot be used with --k, --target, --list or --count".to_owned());
+    }
+    if options.k.is_some() && options.part != PartSelection::Both {
+        return Err("--k and --part are mutually exclusive".to_owned());
+    }
+    if day2_query && options.part != PartSelection::Both {
+        return Err("--policy and --audit cannot be used with --part".to_owned());
+    }
+
+    Ok(options)
+}
+
+fn parse_verify_options(args: &[String]) -> Result<VerifyOptions, String> {
+    let mut days = DaySelection::All;
+    let mut answers = DEFAULT_ANSWERS_PATH.to_owned();
+    let mut timeout = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--day" => days = DaySelection::Single(parse_day(next_value(arg, &mut args)?)?),
+            "--answers" => answers = next_value(arg, &mut args)?.clone(),
+            "--timeout" => timeout = Some(parse_timeout(next_value(arg, &mut args)?)?),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(VerifyOptions {
+        days,
+        answers,
+        timeout,
+    })
+}
+
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "csv" => Ok(OutputFormat::Csv),
+        "json" => Ok(OutputFormat::Json),
+        other => Err(format!("Invalid format: {}", other)),
+    }
+}
+
+fn parse_bench_options(args: &[String]) -> Result<BenchOptions, String> {
+    let mut days = DaySelection::All;
+    let mut iterations = 10;
+    let mut format = OutputFormat::Text;
+    let mut output = None;
+    let mut save = false;
+    let mut history = DEFAULT_HISTORY_PATH.to_owned();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--day" => days = DaySelection::Single(parse_day(next_value(arg, &mut args)?)?),
+            "--iterations" => {
+                let value = next_value(arg, &mut args)?;
+                iterations = match value.parse() {
+                    Ok(iterations) if iterations > 0 => iterations,
+                    _ => return Err(format!("Invalid iteration count: {}", value)),
+                };
+            }
+            "--format" => format = parse_format(next_value(arg, &mut args)?)?,
+            "--output" => output = Some(next_value(arg, &mut args)?.clone()),
+            "--save" => save = true,
+            "--history" => history = next_value(arg, &mut args)?.clone(),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(BenchOptions {
+        days,
+        iterations,
+        format,
+        output,
+        save,
+        history,
+    })
+}
+
+fn parse_compare_options(args: &[String]) -> Result<CompareOptions, String> {
+    let mut options = CompareOptions {
+        history: DEFAULT_HISTORY_PATH.to_owned(),
+        baseline: None,
+        current: None,
+        threshold: 10.0,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--history" => options.history = next_value(arg, &mut args)?.clone(),
+            "--baseline" => options.baseline = Some(next_value(arg, &mut args)?.clone()),
+            "--current" => options.current = Some(next_value(arg, &mut args)?.clone()),
+            "--threshold" => {
+                let value = next_value(arg, &mut args)?;
+                options.threshold = match value.parse::<f64>() {
+                    Ok(threshold) if threshold >= 0.0 => threshold,
+                    _ => return Err(format!("Invalid threshold: {}", value)),
+                };
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_generate_options(args: &[String]) -> Result<GenerateOptions, String> {
+    let mut day = None;
+    let mut seed = 0;
+    let mut size = DEFAULT_GENERATE_SIZE;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--day" => day = Some(parse_day(next_value(arg, &mut args)?)?),
+            "--seed" => {
+                let value = next_value(arg, &mut args)?;
+                seed = value
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {}", value))?;
+            }
+            "--size" => {
+                let value = next_value(arg, &mut args)?;
+                size = value
+                    .parse()
+                    .map_err(|_| format!("Invalid size: {}", value))?;
+            }
+            "--output" => output = Some(next_value(arg, &mut args)?.clone()),
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    let day = day.ok_or_else(|| "--day must be provided".to_owned())?;
+    Ok(GenerateOptions {
+        day,
+        seed,
+        size,
+        output,
+    })
+}
+
+fn parse_new_day(args: &[String]) -> Result<u32, String> {
+    match args {
+        [day] => match parse_day(day)? {
+            day @ 1..=25 => Ok(day),
+            _ => Err(format!("Invalid day: {}", day)),
+        },
+        [] => Err("Missing day for new".to_owned()),
+        [_, other, ..] => Err(format!("Unknown argument: {}", other)),
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("run") => Ok(Command::Run(parse_run_options(&args[1..])?)),
+        Some("verify") => Ok(Command::Verify(parse_verify_options(&args[1..])?)),
+        Some("bench") if args.get(1).map(String::as_str) == Some("compare") => {
+            Ok(Command::BenchCompare(parse_compare_options(&args[2..])?))
+        }
+        Some("bench") => Ok(Command::Bench(parse_bench_options(&args[1..])?)),
+        Some("generate") => Ok(Command::Generate(parse_generate_options(&args[1..])?)),
+        Some("new") => Ok(Command::New(parse_new_day(&args[1..])?)),
+        Some("help") | Some("--help") | Some("-h") | None => Ok(Command::Help),
+        Some(other) => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Command, String> {
+        let args: Vec<String> = line.split_whitespace().map(str::to_owned).collect();
+        parse_args(&args)
+    }
+
+    fn run(line: &str) -> RunOptions {
+        match parse(line) {
+            Ok(Command::Run(options)) => options,
+            other => panic!("expected run options, got {:?}", other),
+        }
+    }
+
+    fn rejects(line: &str, message: &str) {
+        assert_eq!(parse(line), Err(message.to_owned()), "{}", line);
+    }
+
+    #[test]
+    fn run_options() {
+        let options = run("run --day 3 --part 2 --input - --format json --timeout 1.5");
+        assert_eq!(options.days, DaySelection::Single(3));
+        assert_eq!(options.part, PartSelection::Part2);
+        assert_eq!(options.input_path(3), "-");
+        assert_eq!(options.format, OutputFormat::Json);
+        assert_eq!(options.timeout, Some(Duration::from_millis(1500)));
+        assert!(!options.expense_query());
+
+        let options = run("run --all --jobs 4");
+        assert_eq!((options.days, options.jobs), (DaySelection::All, Some(4)));
+        assert_eq!(options.input_path(7), default_input_path(7));
+
+        let options = run("run --day 1 --k 3 --target 100 --count");
+        assert_eq!((options.k, options.target), (Some(3), Some(100)));
+        assert_eq!(options.expense_mode, ExpenseMode::Count);
+        assert!(options.expense_query());
+        assert!(run("run --day 1 --list").expense_query());
+
+        let options = run("run --day 2 --audit --policy count --policy xor --format csv");
+        assert!(options.audit);
+        assert_eq!(options.policies, vec!["count", "xor"]);
+    }
+
+    #[test]
+    fn invalid_values() {
+        rejects("run --day x", "Invalid day: x");
+        rejects("run --day 1 --part 3", "Invalid part: 3");
+        rejects("run --day 1 --format xml", "Invalid format: xml");
+        rejects("run --day 1 --timeout 0", "Invalid timeout: 0");
+        rejects("run --all --jobs 0", "Invalid job count: 0");
+        rejects("run --day 1 --k 0", "Invalid entry count: 0");
+        rejects("run --day 1 --target", "Missing value for --target");
+        rejects("run --day 1 --verbose", "Unknown argument: --verbose");
+        rejects("fly", "Unknown command: fly");
+        assert!(parse("run --day 2 --policy nope").is_err());
+    }
+
+    #[test]
+    fn exclusive_run_flags() {
+        rejects(
+            "run --all --day 1",
+            "--all and --day are mutually exclusive",
+        );
+        rejects("run", "Either --all or --day must be provided");
+        rejects("run --all --input x", "--input cannot be used with --all");
+        rejects("run --day 1 --jobs 2", "--jobs can only be used with --all");
+        rejects(
+            "run --day 1 --list --count",
+            "--list and --count are mutually exclusive",
+        );
+        rejects(
+            "run --day 1 --k 3 --part 1",
+            "--k and --part are mutually exclusive",
+        );
+        rejects(
+            "run --day 1 --count --timeout 5",
+            "--timeout cannot be used with --k, --target, --list or --count",
+        );
+        rejects(
+            "run --day 2 --policy xor --part 1",
+            "--policy and --audit cannot be used with --part",
+        );
+        rejects(
+            "run --day 2 --audit --part 2",
+            "--policy and --audit cannot be used with --part",
+        );
+        rejects(
+            "run --day 2 --format csv",
+            "run only supports csv output with --audit",
+        );
+        rejects(
+            "run --all --format csv",
+            "run only supports csv output with --audit",
+        );
+    }
+
+    #[test]
+    fn day_specific_flags() {
+        for flags in &["--k 2", "--target 10", "--list", "--count"] {
+            rejects(
+                &format!("run --day 3 {}", flags),
+                "--k, --target, --list and --count can only be used with --day 1",
+            );
+        }
+        rejects(
+            "run --all --target 10",
+            "--k, --target, --list and --count can only be used with --day 1",
+        );
+        for flags in &["--policy xor", "--audit"] {
+            rejects(
+                &format!("run --day 1 {}", flags),
+                "--policy and --audit can only be used with --day 2",
+            );
+        }
+    }
+
+    #[test]
+    fn other_commands() {
+        assert_eq!(parse(""), Ok(Command::Help));
+        assert_eq!(parse("new 5"), Ok(Command::New(5)));
+        rejects("new 26", "Invalid day: 26");
+        rejects("new", "Missing day for new");
+
+        match parse("bench compare --baseline abc --threshold 5") {
+            Ok(Command::BenchCompare(options)) => {
+                assert_eq!(options.baseline.as_deref(), Some("abc"));
+                assert_eq!(options.threshold, 5.0);
+            }
+            other => panic!("expected bench compare, got {:?}", other),
+        }
+        match parse("generate --day 8 --seed 7") {
+            Ok(Command::Generate(options)) => {
+                assert_eq!(
+                    (options.day, options.seed, options.size),
+                    (8, 7, DEFAULT_GENERATE_SIZE)
+                );
+            }
+            other => panic!("expected generate, got {:?}", other),
+        }
+        rejects("generate --seed 7", "--day must be provided");
+    }
+}
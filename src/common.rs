@@ -1,19 +1,409 @@
-pub type AdventResult<T> = Result<T, Box<dyn std::error::Error>>;
-
-pub fn split_blocks(content: &str) -> Vec<Vec<&str>> {
-    let mut blocks = Vec::new();
-    let mut current_block = Vec::new();
-    for line in content.lines() {
-        if line.trim().is_empty() && !current_block.is_empty() {
-            blocks.push(current_block);
-            current_block = Vec::new();
+use regex::Regex;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+
+mod budget;
+mod geom;
+mod grid;
+mod rng;
+
+pub use budget::{check_deadline, checkpoint, with_budget};
+pub use geom::{Compass, Point, Turn, Vec2};
+pub use grid::Grid;
+pub use rng::Rng;
+
+pub type AdventResult<T> = Result<T, AdventError>;
+
+#[derive(Debug)]
+pub enum AdventError {
+    Io {
+        path: Option<String>,
+        source: std::io::Error,
+    },
+    Parse(ParseError),
+    NoSolution,
+    Solver(String),
+    /// A result too large for the integer type computing it; names the value.
+    Overflow(String),
+    Timeout(std::time::Duration),
+    UnknownDay(u32),
+}
+
+/// A malformed piece of input. `line` and `column` are 1-based and filled
+/// in as the error bubbles up from the line parser to the file reader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub text: String,
+    pub message: String,
+}
+
+impl AdventError {
+    pub fn parse(message: impl Into<String>, text: &str) -> Self {
+        AdventError::Parse(ParseError {
+            file: None,
+            line: None,
+            column: None,
+            text: text.to_owned(),
+            message: message.into(),
+        })
+    }
+
+    /// Parse error on `fragment`, which must be a subslice of `line`.
+    pub fn parse_at(message: impl Into<String>, line: &str, fragment: &str) -> Self {
+        let mut err = AdventError::parse(message, fragment);
+        if let AdventError::Parse(parse_err) = &mut err {
+            parse_err.column = Some(column_of(line, fragment));
+        }
+        err
+    }
+
+    pub fn at_line(mut self, line: usize) -> Self {
+        if let AdventError::Parse(parse_err) = &mut self {
+            parse_err.line.get_or_insert(line);
+        }
+        self
+    }
+
+    pub fn in_file(mut self, file: &str) -> Self {
+        match &mut self {
+            AdventError::Io { path, .. } => {
+                path.get_or_insert_with(|| file.to_owned());
+            }
+            AdventError::Parse(parse_err) => {
+                parse_err.file.get_or_insert_with(|| file.to_owned());
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for AdventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdventError::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path, source),
+            AdventError::Io { path: None, source } => write!(f, "{}", source),
+            AdventError::Parse(parse_err) => write!(f, "{}", parse_err),
+            AdventError::NoSolution => write!(f, "no solution found"),
+            AdventError::Solver(message) => write!(f, "solver failed: {}", message),
+            AdventError::Overflow(what) => write!(f, "{} overflows", what),
+            AdventError::Timeout(budget) => write!(f, "timed out after {:?}", budget),
+            AdventError::UnknownDay(day) => write!(f, "day {} is not implemented", day),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        if self.file.is_some() || self.line.is_some() || self.column.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}: {:?}", self.message, self.text)
+    }
+}
+
+impl std::error::Error for AdventError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdventError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AdventError {
+    fn from(source: std::io::Error) -> Self {
+        AdventError::Io { path: None, source }
+    }
+}
+
+/// 1-based column of `fragment` inside `line`; `fragment` must be a subslice of `line`.
+pub fn column_of(line: &str, fragment: &str) -> usize {
+    let offset = (fragment.as_ptr() as usize).saturating_sub(line.as_ptr() as usize);
+    line.get(..offset)
+        .map_or(0, |prefix| prefix.chars().count())
+        + 1
+}
+
+/// 1-based line number of `line` inside `content`; `line` must be a subslice of `content`.
+pub fn line_number_of(content: &str, line: &str) -> usize {
+    let offset = (line.as_ptr() as usize).saturating_sub(content.as_ptr() as usize);
+    content
+        .get(..offset)
+        .map_or(0, |prefix| prefix.matches('\n').count())
+        + 1
+}
+
+/// Parses `token`, a subslice of `line`, reporting its column on failure.
+pub fn parse_token<T>(line: &str, token: &str) -> AdventResult<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    token
+        .parse()
+        .map_err(|err: T::Err| AdventError::parse_at(err.to_string(), line, token))
+}
+
+/// Parses every line of `content` with `parser`, tagging errors with the
+/// 1-based number of the failing line.
+pub fn parse_lines_with<T>(
+    content: &str,
+    mut parser: impl FnMut(&str) -> AdventResult<T>,
+) -> AdventResult<Vec<T>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| parser(line).map_err(|err| err.at_line(index + 1)))
+        .collect()
+}
+
+/// Parses every line of `content` as a whole `T`.
+pub fn parse_lines<T>(content: &str) -> AdventResult<Vec<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_lines_with(content, |line| parse_token(line, line))
+}
+
+/// Splits `text`, a subslice of `line`, around the first `separator`.
+pub fn split_pair<'a>(
+    line: &str,
+    text: &'a str,
+    separator: &str,
+) -> AdventResult<(&'a str, &'a str)> {
+    text.split_once(separator).ok_or_else(|| {
+        AdventError::parse_at(format!("Expected '{}'", separator.trim()), line, text)
+    })
+}
+
+/// The named groups of a regex match on `line`; errors point at the group.
+#[derive(Debug)]
+pub struct LineCaptures<'a> {
+    line: &'a str,
+    captures: regex::Captures<'a>,
+}
+
+/// Matches `re` against `line`, failing with `message` when it does not match.
+pub fn capture<'a>(re: &Regex, line: &'a str, message: &str) -> AdventResult<LineCaptures<'a>> {
+    let captures = re
+        .captures(line)
+        .ok_or_else(|| AdventError::parse(message, line))?;
+    Ok(LineCaptures { line, captures })
+}
+
+impl<'a> LineCaptures<'a> {
+    pub fn str(&self, name: &str) -> AdventResult<&'a str> {
+        self.captures
+            .name(name)
+            .map(|group| group.as_str())
+            .ok_or_else(|| AdventError::parse(format!("Missing {}", name), self.line))
+    }
+
+    pub fn parse<T>(&self, name: &str) -> AdventResult<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        parse_token(self.line, self.str(name)?)
+    }
+
+    pub fn char(&self, name: &str) -> AdventResult<char> {
+        let group = self.str(name)?;
+        let mut chars = group.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(AdventError::parse_at(
+                "Expected a single char",
+                self.line,
+                group,
+            )),
+        }
+    }
+}
+
+/// Path that stands for standard input on the command line.
+pub const STDIN_PATH: &str = "-";
+
+/// Name of `path` in error messages.
+pub fn source_name(path: &str) -> &str {
+    if path == STDIN_PATH {
+        "<stdin>"
+    } else {
+        path
+    }
+}
+
+/// Reads all of `path`, or of standard input when `path` is `-`.
+pub fn read_source(path: &str) -> AdventResult<String> {
+    let content = if path == STDIN_PATH {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| content)
+    } else {
+        std::fs::read_to_string(path)
+    };
+    content.map_err(|err| AdventError::from(err).in_file(source_name(path)))
+}
+
+/// Reads the file at `path` and hands its content to `parser`, tagging any
+/// error with the path.
+pub fn read_and_parse<T>(path: &str, parser: fn(&str) -> AdventResult<T>) -> AdventResult<T> {
+    let content = read_source(path)?;
+    parser(&content).map_err(|err| err.in_file(source_name(path)))
+}
+
+/// Lazily groups the lines of `content` into blocks separated by blank lines.
+///
+/// A leading BOM is skipped, `\r\n` line endings and trailing whitespace are
+/// trimmed, and any run of whitespace-only lines counts as one separator. The
+/// returned lines are still subslices of `content`.
+pub fn split_blocks(content: &str) -> Blocks<'_> {
+    Blocks {
+        lines: content.strip_prefix('\u{feff}').unwrap_or(content).lines(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Blocks<'a> {
+    lines: std::str::Lines<'a>,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut block = Vec::new();
+        for line in &mut self.lines {
+            let line = line.trim_end();
+            if !line.is_empty() {
+                block.push(line);
+            } else if !block.is_empty() {
+                return Some(block);
+            }
+        }
+
+        if block.is_empty() {
+            None
         } else {
-            current_block.push(line);
+            Some(block)
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartSelection {
+    Part1,
+    Part2,
+    Both,
+}
+
+impl PartSelection {
+    pub fn part1(self) -> bool {
+        self != PartSelection::Part2
+    }
+
+    pub fn part2(self) -> bool {
+        self != PartSelection::Part1
+    }
+}
+
+/// Unwraps the parse error of a failed parse, for tests.
+#[cfg(test)]
+pub fn expect_parse_error<T: fmt::Debug>(result: AdventResult<T>) -> ParseError {
+    match result {
+        Err(AdventError::Parse(err)) => err,
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_fragments() {
+        let content = "first\nsecond line\n";
+        let line = &content[6..17];
+        assert_eq!(line_number_of(content, line), 2);
+        assert_eq!(column_of(line, &line[7..]), 8);
+    }
+
+    #[test]
+    fn parse_error_display() {
+        let err = expect_parse_error::<u32>(parse_token("ab 12x", &"ab 12x"[3..]));
+        assert_eq!(err.column, Some(4));
+        assert_eq!(err.text, "12x");
+
+        let err = AdventError::Parse(err).at_line(3).in_file("input.txt");
+        assert_eq!(
+            err.to_string(),
+            "input.txt:3:4: invalid digit found in string: \"12x\""
+        );
+    }
+
+    #[test]
+    fn parse_lines_locates_failures() {
+        assert_eq!(parse_lines::<u32>("1\n2\n").unwrap(), vec![1, 2]);
+
+        let err = expect_parse_error(parse_lines::<u32>("1\n2\nthree\n"));
+        assert_eq!((err.line, err.column), (Some(3), Some(1)));
+        assert_eq!(err.text, "three");
+    }
+
+    #[test]
+    fn captures_and_pairs() {
+        let re = Regex::new(r"(?P<name>\w+): (?P<size>\w+)").unwrap();
+        let captures = capture(&re, "box: 12", "Expected '<name>: <size>'").unwrap();
+        assert_eq!(captures.str("name").unwrap(), "box");
+        assert_eq!(captures.parse::<u32>("size").unwrap(), 12);
+        assert!(captures.char("name").is_err());
+
+        let captures = capture(&re, "box: 1x", "").unwrap();
+        let err = expect_parse_error(captures.parse::<u32>("size"));
+        assert_eq!(err.column, Some(6));
+
+        assert_eq!(split_pair("a = b", "a = b", " = ").unwrap(), ("a", "b"));
+        let err = expect_parse_error(split_pair("a b", "a b", " = "));
+        assert_eq!(err.message, "Expected '='");
+    }
+
+    #[test]
+    fn read_errors_name_the_source() {
+        let err = read_and_parse("does/not/exist.txt", |_| Ok(())).unwrap_err();
+        assert!(err.to_string().starts_with("does/not/exist.txt: "));
+        assert_eq!(source_name(STDIN_PATH), "<stdin>");
+    }
+
+    #[test]
+    fn split_blocks_on_blank_lines() {
+        let blocks: Vec<_> = split_blocks("a\nb\n\nc\n").collect();
+        assert_eq!(blocks, vec![vec!["a", "b"], vec!["c"]]);
+    }
 
-    if !current_block.is_empty() {
-        blocks.push(current_block);
+    #[test]
+    fn split_blocks_tolerates_formatting() {
+        let content = "\u{feff}\r\n\na b \r\nc\r\n \r\n\t\n\nd\t\r\n\n";
+        let blocks: Vec<_> = split_blocks(content).collect();
+        assert_eq!(blocks, vec![vec!["a b", "c"], vec!["d"]]);
+        assert_eq!(line_number_of(content, blocks[1][0]), 8);
     }
-    blocks
 }
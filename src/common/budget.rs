@@ -0,0 +1,83 @@
+use super::{AdventError, AdventResult};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// How many `checkpoint` calls go by between two looks at the clock.
+const CHECK_INTERVAL: u32 = 1024;
+
+thread_local! {
+    static DEADLINE: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+    static SKIPPED: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Runs `f` on this thread with a wall-clock `budget`, or none when `None`.
+/// Solvers notice the budget is spent by calling `checkpoint`.
+pub fn with_budget<T>(budget: Option<Duration>, f: impl FnOnce() -> T) -> T {
+    let deadline = budget.map(|budget| (Instant::now() + budget, budget));
+    let previous = DEADLINE.with(|cell| cell.replace(deadline));
+    SKIPPED.with(|cell| cell.set(0));
+
+    // Restores the outer budget even if `f` panics.
+    struct Restore(Option<(Instant, Duration)>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DEADLINE.with(|cell| cell.set(self.0));
+        }
+    }
+    let _restore = Restore(previous);
+
+    f()
+}
+
+/// Fails with `AdventError::Timeout` once the budget given to `with_budget`
+/// is spent. Meant for coarse loops where each iteration does real work.
+pub fn check_deadline() -> AdventResult<()> {
+    match DEADLINE.with(Cell::get) {
+        Some((deadline, budget)) if Instant::now() >= deadline => Err(AdventError::Timeout(budget)),
+        _ => Ok(()),
+    }
+}
+
+/// Like `check_deadline`, but only reads the clock every `CHECK_INTERVAL`
+/// calls, so it can sit in the tightest loops.
+pub fn checkpoint() -> AdventResult<()> {
+    let skipped = SKIPPED.with(|cell| {
+        let skipped = cell.get() + 1;
+        cell.set(skipped % CHECK_INTERVAL);
+        skipped
+    });
+    if skipped < CHECK_INTERVAL {
+        Ok(())
+    } else {
+        check_deadline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_runaway_loop() {
+        let result = with_budget(Some(Duration::from_millis(20)), || -> AdventResult<()> {
+            loop {
+                checkpoint()?;
+            }
+        });
+        assert!(matches!(result, Err(AdventError::Timeout(_))));
+
+        // The budget does not outlive `with_budget`.
+        for _ in 0..10 * CHECK_INTERVAL {
+            checkpoint().unwrap();
+        }
+    }
+
+    #[test]
+    fn no_budget_never_times_out() {
+        with_budget(None, || {
+            for _ in 0..10 * CHECK_INTERVAL {
+                checkpoint().unwrap();
+            }
+        });
+    }
+}
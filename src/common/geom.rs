@@ -0,0 +1,264 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
+/// A displacement on the plane. `y` grows downwards (south), like rows of
+/// puzzle text, so turning right goes from east to south.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Vec2 {
+    pub x: i64,
+    pub y: i64,
+}
+
+/// A position on the plane, see `Vec2` for the orientation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2::new(0, 0);
+
+    /// The 4 orthogonal unit steps, clockwise from north.
+    pub const ORTHOGONAL: [Vec2; 4] = [
+        Vec2::new(0, -1),
+        Vec2::new(1, 0),
+        Vec2::new(0, 1),
+        Vec2::new(-1, 0),
+    ];
+
+    /// The 8 steps to the surrounding cells, clockwise from north-west.
+    pub const SURROUNDING: [Vec2; 8] = [
+        Vec2::new(-1, -1),
+        Vec2::new(0, -1),
+        Vec2::new(1, -1),
+        Vec2::new(1, 0),
+        Vec2::new(1, 1),
+        Vec2::new(0, 1),
+        Vec2::new(-1, 1),
+        Vec2::new(-1, 0),
+    ];
+
+    pub const fn new(x: i64, y: i64) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub fn manhattan_len(self) -> i64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    /// `manhattan_len`, or `None` if it does not fit an `i64`.
+    pub fn checked_manhattan_len(self) -> Option<i64> {
+        self.x.checked_abs()?.checked_add(self.y.checked_abs()?)
+    }
+
+    pub fn checked_add(self, rhs: Vec2) -> Option<Vec2> {
+        Some(Vec2::new(
+            self.x.checked_add(rhs.x)?,
+            self.y.checked_add(rhs.y)?,
+        ))
+    }
+
+    pub fn checked_mul(self, rhs: i64) -> Option<Vec2> {
+        Some(Vec2::new(
+            self.x.checked_mul(rhs)?,
+            self.y.checked_mul(rhs)?,
+        ))
+    }
+
+    pub fn rotate_left(self) -> Self {
+        Vec2::new(self.y, -self.x)
+    }
+
+    pub fn rotate_right(self) -> Self {
+        Vec2::new(-self.y, self.x)
+    }
+
+    /// Rotates by `quarters` quarter turns towards `turn`.
+    pub fn rotate(self, turn: Turn, quarters: u32) -> Self {
+        (0..quarters % 4).fold(self, |v, _| match turn {
+            Turn::Left => v.rotate_left(),
+            Turn::Right => v.rotate_right(),
+        })
+    }
+
+    /// `rotate`, or `None` if a coordinate to negate is `i64::MIN`.
+    pub fn checked_rotate(self, turn: Turn, quarters: u32) -> Option<Self> {
+        (0..quarters % 4).try_fold(self, |v, _| match turn {
+            Turn::Left => Some(Vec2::new(v.y, v.x.checked_neg()?)),
+            Turn::Right => Some(Vec2::new(v.y.checked_neg()?, v.x)),
+        })
+    }
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point::new(0, 0);
+
+    pub const fn new(x: i64, y: i64) -> Self {
+        Point { x, y }
+    }
+
+    pub fn manhattan_distance(self, other: Point) -> i64 {
+        (self - other).manhattan_len()
+    }
+
+    pub fn checked_add(self, rhs: Vec2) -> Option<Point> {
+        Some(Point::new(
+            self.x.checked_add(rhs.x)?,
+            self.y.checked_add(rhs.y)?,
+        ))
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Vec2) -> Vec2 {
+        Vec2::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, rhs: Vec2) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Vec2) -> Vec2 {
+        Vec2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<i64> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: i64) -> Vec2 {
+        Vec2::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Add<Vec2> for Point {
+    type Output = Point;
+
+    fn add(self, rhs: Vec2) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign<Vec2> for Point {
+    fn add_assign(&mut self, rhs: Vec2) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Point {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Point) -> Vec2 {
+        Vec2::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+/// A direction relative to the current heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compass {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Compass {
+    /// Clockwise from north, in the same order as `Vec2::ORTHOGONAL`.
+    pub const ALL: [Compass; 4] = [Compass::North, Compass::East, Compass::South, Compass::West];
+
+    fn index(self) -> usize {
+        match self {
+            Compass::North => 0,
+            Compass::East => 1,
+            Compass::South => 2,
+            Compass::West => 3,
+        }
+    }
+
+    /// The unit step in this direction.
+    pub fn step(self) -> Vec2 {
+        Vec2::ORTHOGONAL[self.index()]
+    }
+
+    /// Turns by `quarters` quarter turns towards `turn`.
+    pub fn turn(self, turn: Turn, quarters: u32) -> Self {
+        let quarters = (quarters % 4) as usize;
+        let index = match turn {
+            Turn::Left => self.index() + 4 - quarters,
+            Turn::Right => self.index() + quarters,
+        };
+        Compass::ALL[index % 4]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let p = Point::new(1, 2) + Vec2::new(3, -4) * 2;
+        assert_eq!(p, Point::new(7, -6));
+        assert_eq!(p - Point::new(7, 0), Vec2::new(0, -6));
+        assert_eq!(p.manhattan_distance(Point::ORIGIN), 13);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let p = Point::new(1, 2).checked_add(Vec2::new(3, -4).checked_mul(2).unwrap());
+        assert_eq!(p, Some(Point::new(7, -6)));
+        assert_eq!(Point::new(i64::MAX, 0).checked_add(Vec2::new(1, 0)), None);
+        assert_eq!(Vec2::new(1, i64::MAX).checked_mul(2), None);
+        assert_eq!(Vec2::new(3, -4).checked_manhattan_len(), Some(7));
+        assert_eq!(Vec2::new(i64::MIN, 0).checked_manhattan_len(), None);
+        assert_eq!(Vec2::new(i64::MAX, 1).checked_manhattan_len(), None);
+        assert_eq!(
+            Vec2::new(10, -4).checked_rotate(Turn::Right, 1),
+            Some(Vec2::new(4, 10))
+        );
+        assert_eq!(Vec2::new(i64::MIN, 0).checked_rotate(Turn::Left, 1), None);
+        assert_eq!(
+            Vec2::new(i64::MIN, 0).checked_rotate(Turn::Right, 1),
+            Some(Vec2::new(0, i64::MIN))
+        );
+    }
+
+    #[test]
+    fn rotations() {
+        let east = Compass::East.step();
+        assert_eq!(east.rotate_right(), Compass::South.step());
+        assert_eq!(east.rotate_left(), Compass::North.step());
+        assert_eq!(Vec2::new(10, -4).rotate(Turn::Right, 1), Vec2::new(4, 10));
+        assert_eq!(Vec2::new(3, 1).rotate(Turn::Left, 4), Vec2::new(3, 1));
+    }
+
+    #[test]
+    fn compass_turns() {
+        assert_eq!(Compass::East.turn(Turn::Right, 1), Compass::South);
+        assert_eq!(Compass::North.turn(Turn::Left, 1), Compass::West);
+        assert_eq!(Compass::West.turn(Turn::Left, 3), Compass::North);
+        assert_eq!(Compass::South.turn(Turn::Right, 2), Compass::North);
+    }
+}
@@ -0,0 +1,245 @@
+use super::{AdventError, AdventResult, Vec2};
+use std::ops::{Index, IndexMut};
+
+/// A rectangular 2D map stored row by row. Positions are `(x, y)` with `y`
+/// growing downwards, like the puzzle text it is parsed from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    cells: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let mut cells = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                cells.push(f(x, y));
+            }
+        }
+        Grid {
+            cells,
+            width,
+            height,
+        }
+    }
+
+    /// Parses one row per line, mapping each char with `cell`. Every line must
+    /// be non-empty and as wide as the first one.
+    pub fn parse(content: &str, cell: impl Fn(char) -> Option<T>) -> AdventResult<Self> {
+        let mut cells = Vec::new();
+        let mut width = 0;
+        let mut height = 0;
+
+        for (index, line) in content.lines().enumerate() {
+            let mut line_width = 0;
+            for (offset, c) in line.char_indices() {
+                let value = cell(c).ok_or_else(|| {
+                    let fragment = &line[offset..offset + c.len_utf8()];
+                    AdventError::parse_at("Unknown grid char", line, fragment).at_line(index + 1)
+                })?;
+                cells.push(value);
+                line_width += 1;
+            }
+
+            if line_width == 0 {
+                return Err(AdventError::parse("Empty grid line", line).at_line(index + 1));
+            }
+            if height > 0 && line_width != width {
+                let err = AdventError::parse(format!("Expected {} cells", width), line);
+                return Err(err.at_line(index + 1));
+            }
+            width = line_width;
+            height += 1;
+        }
+
+        Ok(Grid {
+            cells,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height
+    }
+
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        if self.contains(x, y) {
+            Some(&self.cells[y as usize * self.width + x as usize])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        if self.contains(x, y) {
+            Some(&mut self.cells[y as usize * self.width + x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// The cell at `(x, y)`, as if the grid repeated forever in every direction.
+    pub fn get_wrapping(&self, x: isize, y: isize) -> &T {
+        let x = x.rem_euclid(self.width as isize);
+        let y = y.rem_euclid(self.height as isize);
+        &self[(x as usize, y as usize)]
+    }
+
+    /// `(x, y)` moved by `step`, if still inside the grid.
+    pub fn offset(&self, x: usize, y: usize, step: Vec2) -> Option<(usize, usize)> {
+        let (nx, ny) = (x as isize + step.x as isize, y as isize + step.y as isize);
+        if self.contains(nx, ny) {
+            Some((nx as usize, ny as usize))
+        } else {
+            None
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(x < self.width);
+        self.cells.iter().skip(x).step_by(self.width)
+    }
+
+    pub fn neighbors4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbors(x, y, &Vec2::ORTHOGONAL)
+    }
+
+    pub fn neighbors8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbors(x, y, &Vec2::SURROUNDING)
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        x: usize,
+        y: usize,
+        steps: &'a [Vec2],
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        steps
+            .iter()
+            .filter_map(move |&step| self.offset(x, y, step))
+    }
+
+    /// Positions from `(x, y)` (excluded) moving by `step` until the edge.
+    pub fn ray(&self, x: usize, y: usize, step: Vec2) -> impl Iterator<Item = (usize, usize)> + '_ {
+        std::iter::successors(self.offset(x, y, step), move |&(x, y)| {
+            self.offset(x, y, step)
+        })
+    }
+
+    /// Draws the grid back as text, one line per row.
+    pub fn render(&self, cell: impl Fn(&T) -> char) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            out.extend(row.iter().map(&cell));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is out of the grid",
+            x,
+            y
+        );
+        &self.cells[y * self.width + x]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is out of the grid",
+            x,
+            y
+        );
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::expect_parse_error;
+
+    fn digits() -> Grid<u32> {
+        Grid::parse("123\n456\n", |c| c.to_digit(10)).unwrap()
+    }
+
+    #[test]
+    fn indexing() {
+        let grid = digits();
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid[(2, 1)], 6);
+        assert_eq!(grid.get(-1, 0), None);
+        assert_eq!(grid.get(3, 0), None);
+        assert_eq!(*grid.get_wrapping(4, -1), 5);
+        assert_eq!(grid.row(1), &[4, 5, 6]);
+        assert_eq!(grid.column(1).copied().collect::<Vec<_>>(), vec![2, 5]);
+    }
+
+    #[test]
+    fn neighborhoods() {
+        let grid = digits();
+        assert_eq!(
+            grid.neighbors4(0, 0).collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1)]
+        );
+        assert_eq!(grid.neighbors8(1, 0).count(), 5);
+        assert_eq!(
+            grid.ray(0, 0, Vec2::new(1, 0)).collect::<Vec<_>>(),
+            vec![(1, 0), (2, 0)]
+        );
+        assert_eq!(grid.ray(0, 0, Vec2::new(-1, 1)).count(), 0);
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let grid = digits();
+        let rendered = grid.render(|&d| std::char::from_digit(d, 10).unwrap());
+        assert_eq!(rendered, "123\n456\n");
+    }
+
+    #[test]
+    fn malformed_grid() {
+        let err = expect_parse_error(Grid::parse("12\n1x\n", |c| c.to_digit(10)));
+        assert_eq!((err.line, err.column), (Some(2), Some(2)));
+
+        let err = expect_parse_error(Grid::parse("12\n123\n", |c| c.to_digit(10)));
+        assert_eq!(err.line, Some(2));
+    }
+}
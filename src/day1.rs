@@ -1,51 +1,573 @@
 use crate::common::*;
+use crate::solution::Solution;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::Range;
 
-fn read_input(path: &str) -> AdventResult<Vec<u32>> {
-    let file_content = std::fs::read_to_string(path)?;
-    let mut values = Vec::new();
-    for line in file_content.lines() {
-        let value: u32 = line.parse()?;
-        values.push(value);
-    }
-    Ok(values)
+/// The sum the expense report entries must reach.
+pub const TARGET: i64 = 2020;
+
+/// Entries are signed so that credits can be listed as negative expenses.
+pub fn parse_input(content: &str) -> AdventResult<Vec<i64>> {
+    parse_lines(content)
 }
 
-fn find_sum_of_2(values: &[u32]) -> Option<(u32, u32)> {
-    for &a in values {
-        for &b in values {
-            if a + b == 2020 {
-                return Some((a, b));
+/// Indices, in increasing order, of `k` distinct entries summing to `target`.
+///
+/// Pairs come from a single pass with a hash map of the entries seen so far.
+/// Larger sets sort the entries once, fix all but two of them and close with a
+/// two-pointer scan, which is O(n^(k-1)) instead of O(n^k). Sums are computed
+/// on `i128` so no set of entries can overflow them.
+pub fn find_k_sum(values: &[i64], k: usize, target: i64) -> Option<Vec<usize>> {
+    if k > values.len() {
+        return None;
+    }
+    match k {
+        0 if target == 0 => Some(Vec::new()),
+        0 => None,
+        1 => values
+            .iter()
+            .position(|&value| value == target)
+            .map(|index| vec![index]),
+        2 => find_pair(values, target).map(|(i, j)| vec![i, j]),
+        _ => {
+            let entries = SortedEntries::new(values);
+            let mut chosen = Vec::with_capacity(k);
+            if sorted_k_sum(&entries, 0, k, i128::from(target), &mut chosen) {
+                Some(entries.indices(&chosen))
+            } else {
+                None
             }
         }
     }
+}
+
+fn find_pair(values: &[i64], target: i64) -> Option<(usize, usize)> {
+    let mut seen = HashMap::new();
+    for (j, &value) in values.iter().enumerate() {
+        let rest = i64::try_from(i128::from(target) - i128::from(value));
+        if let Some(&i) = rest.ok().and_then(|rest| seen.get(&rest)) {
+            return Some((i, j));
+        }
+        seen.entry(value).or_insert(j);
+    }
     None
 }
 
-fn find_sum_of_3(values: &[u32]) -> Option<(u32, u32, u32)> {
-    for &a in values {
-        for &b in values {
-            for &c in values {
-                if a + b + c == 2020 {
-                    return Some((a, b, c));
+/// Pushes onto `chosen` the positions of `k >= 2` sorted entries from `start`
+/// on summing to `target`, if there are any.
+fn sorted_k_sum(
+    entries: &SortedEntries,
+    start: usize,
+    k: usize,
+    target: i128,
+    chosen: &mut Vec<usize>,
+) -> bool {
+    let sorted = &entries.sorted;
+    if k == 2 {
+        let (mut low, mut high) = (start, sorted.len());
+        while low + 1 < high {
+            match (sorted[low] + sorted[high - 1]).cmp(&target) {
+                Ordering::Less => low += 1,
+                Ordering::Greater => high -= 1,
+                Ordering::Equal => {
+                    chosen.extend(&[low, high - 1]);
+                    return true;
                 }
             }
         }
+        return false;
+    }
+
+    if start + k > sorted.len() {
+        return false;
+    }
+    for first in start..=sorted.len() - k {
+        if entries.min_sum(first, k) > target {
+            break;
+        }
+        let skip = sorted[first] + entries.max_sum(k - 1) < target
+            || (first > start && sorted[first] == sorted[first - 1]);
+        if skip {
+            continue;
+        }
+        chosen.push(first);
+        if sorted_k_sum(entries, first + 1, k - 1, target - sorted[first], chosen) {
+            return true;
+        }
+        chosen.pop();
+    }
+    false
+}
+
+/// Product of the entries at `indices`, failing rather than wrapping when it
+/// does not fit in an `i128`.
+pub fn product_of(values: &[i64], indices: &[usize]) -> AdventResult<i128> {
+    indices
+        .iter()
+        .try_fold(1i128, |product, &index| {
+            product.checked_mul(i128::from(values[index]))
+        })
+        .ok_or_else(|| AdventError::Overflow("expense product".to_owned()))
+}
+
+/// The entries at `indices` as a sum, e.g. `1721 + 299` or `2030 - 10`.
+pub fn describe(values: &[i64], indices: &[usize]) -> String {
+    let mut out = String::new();
+    for (n, &index) in indices.iter().enumerate() {
+        let value = values[index];
+        match (n, value < 0) {
+            (0, _) => out.push_str(&value.to_string()),
+            (_, true) => out.push_str(&format!(" - {}", value.unsigned_abs())),
+            (_, false) => out.push_str(&format!(" + {}", value)),
+        }
+    }
+    out
+}
+
+/// Product of `k` distinct entries summing to `target`.
+pub fn expense_product(values: &[i64], k: usize, target: i64) -> AdventResult<i128> {
+    let indices = find_k_sum(values, k, target).ok_or(AdventError::NoSolution)?;
+    product_of(values, &indices)
+}
+
+/// Entries sorted by value, with prefix sums to bound what the smallest or
+/// largest entries can add up to.
+struct SortedEntries {
+    order: Vec<usize>,
+    sorted: Vec<i128>,
+    prefix: Vec<i128>,
+}
+
+impl SortedEntries {
+    fn new(values: &[i64]) -> Self {
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by_key(|&index| values[index]);
+        let sorted: Vec<i128> = order
+            .iter()
+            .map(|&index| i128::from(values[index]))
+            .collect();
+        let prefix = std::iter::once(0)
+            .chain(sorted.iter().scan(0, |sum, &value| {
+                *sum += value;
+                Some(*sum)
+            }))
+            .collect();
+        SortedEntries {
+            order,
+            sorted,
+            prefix,
+        }
+    }
+
+    /// Smallest sum of `count` entries taken from `start` on.
+    fn min_sum(&self, start: usize, count: usize) -> i128 {
+        self.prefix[start + count] - self.prefix[start]
+    }
+
+    /// Largest sum of `count` entries.
+    fn max_sum(&self, count: usize) -> i128 {
+        let len = self.sorted.len();
+        self.prefix[len] - self.prefix[len - count]
+    }
+
+    /// Original indices of the sorted `positions`, in increasing order.
+    fn indices(&self, positions: &[usize]) -> Vec<usize> {
+        let mut indices: Vec<usize> = positions
+            .iter()
+            .map(|&position| self.order[position])
+            .collect();
+        indices.sort_unstable();
+        indices
+    }
+}
+
+/// Every set of `k` distinct indices whose entries sum to a target, see
+/// `k_sums`.
+pub struct KSums {
+    entries: SortedEntries,
+    k: usize,
+    target: i128,
+    /// Sorted positions chosen so far, and their sum.
+    stack: Vec<usize>,
+    sum: i128,
+    /// Next position to try at the current depth.
+    next: usize,
+    /// Positions completing the current stack, still to be yielded.
+    last: Range<usize>,
+    done: bool,
+}
+
+impl Iterator for KSums {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        let len = self.entries.sorted.len();
+        loop {
+            if let Some(position) = self.last.next() {
+                self.stack.push(position);
+                let indices = self.entries.indices(&self.stack);
+                self.stack.pop();
+                return Some(indices);
+            }
+            if self.done {
+                return None;
+            }
+
+            let remaining = self.k - self.stack.len();
+            if remaining == 0 {
+                // Only when `k == 0`: the empty set.
+                self.done = true;
+                if self.target == 0 {
+                    return Some(Vec::new());
+                }
+                continue;
+            }
+
+            let fits = self.next + remaining <= len
+                && self.sum + self.entries.min_sum(self.next, remaining) <= self.target;
+            if fits && remaining == 1 {
+                // The entries equal to what is missing form a run.
+                let need = self.target - self.sum;
+                let tail = &self.entries.sorted[self.next..];
+                let start = self.next + tail.partition_point(|&value| value < need);
+                let end = self.next + tail.partition_point(|&value| value <= need);
+                self.last = start..end;
+                self.next = len;
+            } else if fits {
+                self.stack.push(self.next);
+                self.sum += self.entries.sorted[self.next];
+                self.next += 1;
+            } else {
+                match self.stack.pop() {
+                    Some(position) => {
+                        self.sum -= self.entries.sorted[position];
+                        self.next = position + 1;
+                    }
+                    None => self.done = true,
+                }
+            }
+        }
+    }
+}
+
+/// Lazily enumerates every set of `k` distinct indices, each in increasing
+/// order, whose entries sum to `target`. Equal entries at different indices
+/// make different sets.
+pub fn k_sums(values: &[i64], k: usize, target: i64) -> KSums {
+    KSums {
+        entries: SortedEntries::new(values),
+        k,
+        target: i128::from(target),
+        stack: Vec::with_capacity(k.min(values.len())),
+        sum: 0,
+        next: 0,
+        last: 0..0,
+        done: k > values.len(),
+    }
+}
+
+pub fn count_k_sums(values: &[i64], k: usize, target: i64) -> usize {
+    k_sums(values, k, target).count()
+}
+
+/// The achievable sum of `k` distinct entries nearest to `target`, the
+/// smaller one on a tie, with the indices reaching it.
+pub fn closest_k_sum(values: &[i64], k: usize, target: i64) -> Option<(i128, Vec<usize>)> {
+    if k > values.len() {
+        return None;
+    }
+    let entries = SortedEntries::new(values);
+    let mut best = None;
+    closest_sorted(
+        &entries,
+        0,
+        k,
+        0,
+        i128::from(target),
+        &mut Vec::with_capacity(k),
+        &mut best,
+    );
+    best.map(|(sum, positions)| (sum, entries.indices(&positions)))
+}
+
+/// Tries every way to complete `chosen` (summing to `sum`) with `k` entries
+/// from `start` on, keeping in `best` the total nearest to `target`. The
+/// last two entries come from a two-pointer scan.
+fn closest_sorted(
+    entries: &SortedEntries,
+    start: usize,
+    k: usize,
+    sum: i128,
+    target: i128,
+    chosen: &mut Vec<usize>,
+    best: &mut Option<(i128, Vec<usize>)>,
+) {
+    let sorted = &entries.sorted;
+    let mut consider = |total: i128, extra: &[usize], chosen: &[usize]| {
+        let better = match best {
+            Some((best_sum, _)) => {
+                let (distance, best_distance) = (total.abs_diff(target), best_sum.abs_diff(target));
+                distance < best_distance || (distance == best_distance && total < *best_sum)
+            }
+            None => true,
+        };
+        if better {
+            *best = Some((total, chosen.iter().chain(extra).copied().collect()));
+        }
+    };
+
+    match k {
+        0 => consider(sum, &[], chosen),
+        1 => {
+            // The nearest entry is one of the two around `target - sum`.
+            let need = target - sum;
+            let split = start + sorted[start..].partition_point(|&value| value < need);
+            for position in (split.saturating_sub(1).max(start)..sorted.len()).take(2) {
+                consider(sum + sorted[position], &[position], chosen);
+            }
+        }
+        2 => {
+            let (mut low, mut high) = (start, sorted.len());
+            while low + 1 < high {
+                let total = sum + sorted[low] + sorted[high - 1];
+                consider(total, &[low, high - 1], chosen);
+                match total.cmp(&target) {
+                    Ordering::Less => low += 1,
+                    Ordering::Greater => high -= 1,
+                    Ordering::Equal => return,
+                }
+            }
+        }
+        _ => {
+            let last = sorted.len() - k;
+            for (first, &value) in sorted.iter().enumerate().take(last + 1).skip(start) {
+                chosen.push(first);
+                closest_sorted(entries, first + 1, k - 1, sum + value, target, chosen, best);
+                chosen.pop();
+            }
+        }
+    }
+}
+
+/// An expense report with exactly one pair and one triple of entries summing
+/// to `TARGET`; the other entries are too large to be part of either.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let planted = loop {
+        let a = rng.range_i64(1, 401);
+        let b = rng.range_i64(401, 601);
+        let c = rng.range_i64(401, 601);
+        let planted = vec![a, TARGET - a, b, c, TARGET - b - c];
+        let reuses_entry = planted
+            .iter()
+            .any(|&x| x * 2 == TARGET || planted.iter().any(|&y| x * 2 + y == TARGET));
+        if !reuses_entry {
+            break planted;
+        }
+    };
+
+    let mut values = planted.clone();
+    while values.len() < size {
+        let filler = rng.range_i64(TARGET / 2 + 1, TARGET);
+        let completes_sum = planted
+            .iter()
+            .any(|&x| x + filler == TARGET || planted.iter().any(|&y| x + y + filler == TARGET));
+        if !completes_sum {
+            values.push(filler);
+        }
+    }
+
+    rng.shuffle(&mut values);
+    values.iter().map(|value| format!("{}\n", value)).collect()
+}
+
+pub struct Day1;
+
+impl Solution for Day1 {
+    const DAY: u32 = 1;
+
+    type Input = Vec<i64>;
+    type Answer1 = i128;
+    type Answer2 = i128;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_input(content)
+    }
+
+    fn part1(values: &Self::Input) -> AdventResult<i128> {
+        expense_product(values, 2, TARGET)
+    }
+
+    fn part2(values: &Self::Input) -> AdventResult<i128> {
+        expense_product(values, 3, TARGET)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
     }
-    None
 }
 
-pub fn run(path: &str) {
-    let values = read_input(path).expect("Cannot read input file");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    const EXAMPLE: &str = "\
+1721
+979
+366
+299
+675
+1456
+";
+
+    #[test]
+    fn example() {
+        let values = parse_input(EXAMPLE).unwrap();
+        assert_eq!(Day1::part1(&values).unwrap(), 514579);
+        assert_eq!(Day1::part2(&values).unwrap(), 241861950);
+    }
+
+    fn sum_of(values: &[i64], indices: &[usize]) -> i128 {
+        indices.iter().map(|&i| i128::from(values[i])).sum()
+    }
 
-    if let Some((a, b)) = find_sum_of_2(&values) {
-        println!("day1 part1: {}", a * b);
-    } else {
-        println!("day1 part1: no solution found")
+    /// Every set of `k` distinct indices, for cross-checking `find_k_sum`.
+    fn brute_force(values: &[i64], k: usize, target: i64) -> bool {
+        (0..values.len())
+            .combinations(k)
+            .any(|indices| sum_of(values, &indices) == i128::from(target))
+    }
+
+    #[test]
+    fn k_sum() {
+        let values = parse_input(EXAMPLE).unwrap();
+        assert_eq!(find_k_sum(&values, 2, 2020), Some(vec![0, 3]));
+        assert_eq!(find_k_sum(&values, 3, 2020), Some(vec![1, 2, 4]));
+        assert_eq!(expense_product(&values, 1, 366).unwrap(), 366);
+        assert_eq!(find_k_sum(&values, 4, 2020), None);
+
+        // An entry cannot be paired with itself.
+        assert_eq!(find_k_sum(&[1010, 5], 2, 2020), None);
+        assert_eq!(find_k_sum(&[1010, 5, 1010], 2, 2020), Some(vec![0, 2]));
+        assert_eq!(find_k_sum(&[600, 610, 5], 3, 1810), None);
+
+        // More entries than the report holds.
+        assert_eq!(find_k_sum(&[1010, 1010], 3, 2020), None);
+        assert_eq!(find_k_sum(&[1010, 1010], usize::MAX, 2020), None);
+        assert!(matches!(
+            expense_product(&[1010, 1010], usize::MAX, 2020),
+            Err(AdventError::NoSolution)
+        ));
+    }
+
+    #[test]
+    fn k_sum_matches_brute_force() {
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let values: Vec<i64> = (0..rng.range(0, 9))
+                .map(|_| rng.range_i64(-10, 20))
+                .collect();
+            let k = rng.range(0, 5) as usize;
+            let target = rng.range_i64(-20, 40);
+            let found = find_k_sum(&values, k, target);
+            assert_eq!(
+                found.is_some(),
+                brute_force(&values, k, target),
+                "{:?} {} {}",
+                values,
+                k,
+                target
+            );
+            if let Some(indices) = found {
+                assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+                assert_eq!(sum_of(&values, &indices), i128::from(target));
+            }
+        }
+    }
+
+    #[test]
+    fn negative_entries_and_overflow() {
+        let values = parse_input("2030\n-10\n7\n").unwrap();
+        assert_eq!(expense_product(&values, 2, 2020).unwrap(), -20300);
+        assert_eq!(describe(&values, &[0, 1]), "2030 - 10");
+
+        let values = [i64::MAX, i64::MIN, -i64::MAX];
+        assert_eq!(
+            expense_product(&values, 2, -1).unwrap(),
+            i128::from(i64::MAX) * i128::from(i64::MIN)
+        );
+        match expense_product(&[i64::MAX, i64::MAX, -i64::MAX], 3, i64::MAX) {
+            Err(AdventError::Overflow(_)) => {}
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn enumerates_every_solution() {
+        let values = [1000, 1020, 1000, 1010, 10];
+        assert_eq!(
+            k_sums(&values, 2, 2020).collect::<Vec<_>>(),
+            vec![vec![0, 1], vec![1, 2]]
+        );
+        assert_eq!(count_k_sums(&values, 3, 2020), 2);
+        assert_eq!(count_k_sums(&values, 0, 0), 1);
+        assert_eq!(count_k_sums(&values, 6, 2020), 0);
+        assert_eq!(count_k_sums(&values, 100_000_000_000, 2020), 0);
+        assert_eq!(k_sums(&[], usize::MAX, 0).next(), None);
+    }
+
+    #[test]
+    fn closest_sum() {
+        let values = parse_input(EXAMPLE).unwrap();
+        assert_eq!(closest_k_sum(&values, 2, 2020), Some((2020, vec![0, 3])));
+        assert_eq!(closest_k_sum(&values, 2, 700), Some((665, vec![2, 3])));
+        assert_eq!(closest_k_sum(&values, 1, 1000), Some((979, vec![1])));
+        assert_eq!(closest_k_sum(&values, 7, 2020), None);
+    }
+
+    #[test]
+    fn enumeration_matches_brute_force() {
+        let mut rng = Rng::new(2);
+        for _ in 0..200 {
+            let values: Vec<i64> = (0..rng.range(0, 9))
+                .map(|_| rng.range_i64(-10, 20))
+                .collect();
+            let k = rng.range(0, 5) as usize;
+            let target = rng.range_i64(-20, 40);
+
+            let expected: Vec<Vec<usize>> = (0..values.len())
+                .combinations(k)
+                .filter(|indices| sum_of(&values, indices) == i128::from(target))
+                .collect();
+            let mut found: Vec<Vec<usize>> = k_sums(&values, k, target).collect();
+            found.sort();
+            assert_eq!(found, expected, "{:?} {} {}", values, k, target);
+
+            let nearest = (0..values.len())
+                .combinations(k)
+                .map(|indices| sum_of(&values, &indices))
+                .min_by_key(|&sum| (sum.abs_diff(i128::from(target)), sum));
+            let closest = closest_k_sum(&values, k, target);
+            assert_eq!(
+                closest.as_ref().map(|(sum, _)| *sum),
+                nearest,
+                "{:?} {} {}",
+                values,
+                k,
+                target
+            );
+            if let Some((sum, indices)) = closest {
+                assert_eq!(sum_of(&values, &indices), sum);
+            }
+        }
     }
 
-    if let Some((a, b, c)) = find_sum_of_3(&values) {
-        println!("day1 part2: {}", a * b * c);
-    } else {
-        println!("day1 part2: no solution found")
+    #[test]
+    fn malformed_line() {
+        let err = expect_parse_error(parse_input("1721\n97a\n"));
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.text, "97a");
     }
 }
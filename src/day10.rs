@@ -1,17 +1,20 @@
-use crate::common::AdventResult;
-use std::collections::HashMap;
-
-fn parse_input_ratings(path: &str) -> AdventResult<Vec<u64>> {
-    let content = std::fs::read_to_string(path)?;
-    let mut ratings = Vec::new();
-    for line in content.lines() {
-        let rating = line.parse()?;
-        ratings.push(rating);
-    }
-    Ok(ratings)
+use crate::common::*;
+use crate::solution::Solution;
+
+pub fn parse_ratings(content: &str) -> AdventResult<Vec<u64>> {
+    parse_lines_with(content, |line| {
+        let rating: u64 = parse_token(line, line)?;
+        if rating > u64::MAX - 3 {
+            return Err(AdventError::parse(
+                "Rating leaves no room for the device",
+                line,
+            ));
+        }
+        Ok(rating)
+    })
 }
 
-fn build_chain(mut ratings: Vec<u64>) -> Vec<u64> {
+pub fn build_chain(mut ratings: Vec<u64>) -> Vec<u64> {
     // Sorted ratings + [0] + [max + 3]
     ratings.push(0);
     ratings.sort();
@@ -19,7 +22,7 @@ fn build_chain(mut ratings: Vec<u64>) -> Vec<u64> {
     ratings
 }
 
-fn count_diffs(chain: &[u64]) -> Option<[usize; 3]> {
+pub(crate) fn count_diffs(chain: &[u64]) -> Option<[usize; 3]> {
     let mut count = [0; 3];
 
     for window in chain.windows(2) {
@@ -33,7 +36,7 @@ fn count_diffs(chain: &[u64]) -> Option<[usize; 3]> {
     Some(count)
 }
 
-fn compute_signature(chain: &[u64]) -> Option<usize> {
+pub(crate) fn compute_signature(chain: &[u64]) -> Option<usize> {
     let counts = count_diffs(chain);
     if let Some([d1, _, d3]) = counts {
         Some(d3 * d1)
@@ -42,38 +45,194 @@ fn compute_signature(chain: &[u64]) -> Option<usize> {
     }
 }
 
-fn count_part2(ratings: &[u64]) -> usize {
-    fn inner_count(ratings: &[u64], i: usize, cache: &mut HashMap<usize, usize>) -> usize {
-        if cache.contains_key(&i) {
-            return *cache.get(&i).unwrap();
+/// Number of ways to reach the end of `chain`, `None` if it overflows.
+pub(crate) fn count_part2(chain: &[u64]) -> Option<usize> {
+    // ways[i]: number of arrangements from adapter i to the device.
+    let mut ways = vec![0usize; chain.len()];
+    for i in (0..chain.len()).rev() {
+        if i == chain.len() - 1 {
+            ways[i] = 1;
+            continue;
+        }
+        let mut count: usize = 0;
+        for j in (i + 1)..chain.len().min(i + 4) {
+            if chain[j] - chain[i] <= 3 {
+                count = count.checked_add(ways[j])?;
+            }
         }
+        ways[i] = count;
+    }
+    ways.first().copied()
+}
 
-        if i == ratings.len() - 1 {
+/// Walks every arrangement one by one; exponential, but a simple reference
+/// for `count_part2`.
+#[cfg(test)]
+pub(crate) fn count_part2_raw(chain: &[u64]) -> usize {
+    fn walk(chain: &[u64], i: usize) -> usize {
+        if i + 1 >= chain.len() {
             return 1;
         }
+        ((i + 1)..chain.len().min(i + 4))
+            .filter(|&j| chain[j] - chain[i] <= 3)
+            .map(|j| walk(chain, j))
+            .sum()
+    }
 
-        let mut res = 0;
-        for j in (i + 1)..=(i + 3) {
-            if j < ratings.len() && ratings[j] - ratings[i] <= 3 {
-                res += inner_count(&ratings, j, cache);
-            }
+    walk(chain, 0)
+}
+
+/// Ways to cross a run of `n` one-jolt steps between three-jolt gaps.
+const RUN_WAYS: [u64; 5] = [1, 1, 2, 4, 7];
+/// Bound on the part 2 answer of generated inputs, whatever their size.
+const MAX_ARRANGEMENTS: u64 = 1 << 48;
+
+/// `size` adapters, one or three jolts apart with runs of at most four
+/// one-jolt steps, like the puzzle inputs. Once the arrangement count
+/// nears `MAX_ARRANGEMENTS`, one-jolt steps no longer follow each other.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut ratings = Vec::with_capacity(size);
+    let (mut rating, mut run) = (0, 0);
+    // Arrangements of the runs already closed by a three-jolt gap.
+    let mut arrangements = 1;
+    for _ in 0..size {
+        let extend = run < 4 && arrangements * RUN_WAYS[run + 1] <= MAX_ARRANGEMENTS;
+        if extend && rng.chance(70) {
+            rating += 1;
+            run += 1;
+        } else {
+            rating += 3;
+            arrangements *= RUN_WAYS[run];
+            run = 0;
         }
+        ratings.push(rating);
+    }
+
+    rng.shuffle(&mut ratings);
+    ratings
+        .iter()
+        .map(|rating| format!("{}\n", rating))
+        .collect()
+}
+
+pub struct Day10;
 
-        cache.insert(i, res);
-        res
+impl Solution for Day10 {
+    const DAY: u32 = 10;
+
+    type Input = Vec<u64>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        let ratings = parse_ratings(content)?;
+        Ok(build_chain(ratings))
     }
 
-    let mut cache = HashMap::new();
-    inner_count(ratings, 0, &mut cache)
+    fn part1(chain: &Self::Input) -> AdventResult<usize> {
+        compute_signature(chain).ok_or(AdventError::NoSolution)
+    }
+
+    fn part2(chain: &Self::Input) -> AdventResult<usize> {
+        count_part2(chain).ok_or_else(|| AdventError::Overflow("arrangement count".to_owned()))
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let ratings = parse_input_ratings(path).expect("Cannot read ratings");
-    let chain = build_chain(ratings);
-    let signature = compute_signature(&chain).expect("Cannot find a suitable chain");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+16
+10
+15
+5
+1
+11
+7
+19
+6
+12
+4
+";
+
+    const LARGER_EXAMPLE: &str = "\
+28
+33
+18
+42
+31
+14
+46
+20
+48
+47
+24
+23
+49
+45
+19
+38
+39
+11
+1
+32
+25
+35
+8
+17
+7
+9
+4
+2
+34
+10
+3
+";
 
-    println!("day10 part1: {}", signature);
+    #[test]
+    fn example() {
+        let chain = build_chain(parse_ratings(EXAMPLE).unwrap());
+        assert_eq!(Day10::part1(&chain).unwrap(), 35);
+        assert_eq!(Day10::part2(&chain).unwrap(), 8);
 
-    let comb = count_part2(&chain);
-    println!("day10 part2: {}", comb);
+        let chain = build_chain(parse_ratings(LARGER_EXAMPLE).unwrap());
+        assert_eq!(Day10::part1(&chain).unwrap(), 220);
+        assert_eq!(Day10::part2(&chain).unwrap(), 19208);
+    }
+
+    #[test]
+    fn arrangement_counts() {
+        assert_eq!(count_part2(&[0, 3]), Some(1));
+        assert_eq!(count_part2(&[0, 1, 2, 3, 6]), Some(4));
+        assert_eq!(count_part2(&[0, 4, 7]), Some(0));
+
+        let chain = build_chain(parse_ratings(LARGER_EXAMPLE).unwrap());
+        assert_eq!(count_part2(&chain), Some(count_part2_raw(&chain)));
+
+        // Deep enough to overflow the stack of a recursive walk.
+        let spaced: Vec<u64> = (0..200_000).map(|i| i * 3).collect();
+        assert_eq!(count_part2(&spaced), Some(1));
+
+        // Arrangements of consecutive ratings grow like the tribonacci numbers.
+        let consecutive = build_chain((1..200).collect());
+        assert_eq!(count_part2(&consecutive), None);
+        assert!(matches!(
+            Day10::part2(&consecutive),
+            Err(AdventError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_line() {
+        let err = expect_parse_error(parse_ratings("16\n\n10\n"));
+        assert_eq!(err.line, Some(2));
+
+        let err = expect_parse_error(parse_ratings("16\n18446744073709551615\n"));
+        assert_eq!(err.line, Some(2));
+    }
 }
@@ -1,222 +1,195 @@
-use crate::common::AdventResult;
+use crate::common::*;
+use crate::solution::Solution;
 use std::fmt;
-use std::io::{BufRead, BufReader};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Cell {
+pub enum Cell {
     Floor,
     OccupiedSeat,
     EmptySeat,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Map {
-    cells: Vec<Cell>,
-    width: usize,
-    height: usize,
+pub struct Map {
+    cells: Grid<Cell>,
 }
 
 impl Map {
-    fn read_from_path(path: &str) -> AdventResult<Self> {
-        let file = std::fs::File::open(path)?;
-        let reader = BufReader::new(file);
-
-        let mut cells = Vec::new();
-        let mut width = 0;
-        let mut height = 0;
-
-        for line in reader.lines() {
-            let line = line?;
-
-            width = 0;
-            for c in line.chars() {
-                let cell = match c {
-                    'L' => Cell::EmptySeat,
-                    '.' => Cell::Floor,
-                    '#' => Cell::OccupiedSeat,
-                    _ => panic!("Unknown cell char"),
-                };
-
-                cells.push(cell);
-                width += 1;
-            }
-
-            height += 1;
-        }
-
-        Ok(Map {
-            cells,
-            width,
-            height,
-        })
-    }
-
-    fn assert_in_bounds(&self, x: usize, y: usize) {
-        assert!(x < self.width);
-        assert!(y < self.height);
+    pub fn parse(content: &str) -> AdventResult<Self> {
+        let cells = Grid::parse(content, |c| match c {
+            'L' => Some(Cell::EmptySeat),
+            '.' => Some(Cell::Floor),
+            '#' => Some(Cell::OccupiedSeat),
+            _ => None,
+        })?;
+        Ok(Map { cells })
     }
 
-    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
-        self.assert_in_bounds(x, y);
-        self.cells[y * self.width + x] = cell;
+    pub fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        self.cells[(x, y)] = cell;
     }
 
-    fn get_cell(&self, x: usize, y: usize) -> Cell {
-        self.assert_in_bounds(x, y);
-        self.cells[y * self.width + x]
+    pub fn get_cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[(x, y)]
     }
 
     fn get_number_of_inline_neighbors_occupied(&self, x: usize, y: usize) -> usize {
-        let mut counter = 0;
-
-        for &dy in &[-1, 0, 1] {
-            'inner_main: for &dx in &[-1, 0, 1] {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                for coeff in 1.. {
-                    let nx = x as isize + dx * coeff;
-                    let ny = y as isize + dy * coeff;
-
-                    if nx < 0 || nx >= self.width as isize || ny < 0 || ny >= self.height as isize {
-                        continue 'inner_main;
-                    }
-
-                    let nx = nx as usize;
-                    let ny = ny as usize;
-
-                    let cell = self.get_cell(nx, ny);
-                    match cell {
-                        Cell::Floor => continue,
-                        Cell::OccupiedSeat => {
-                            counter += 1;
-                            break;
-                        }
-                        Cell::EmptySeat => {
-                            break;
-                        }
-                    }
-                }
-            }
-        }
-
-        counter
+        Vec2::SURROUNDING
+            .iter()
+            .filter(|&&step| {
+                let first_seat = self
+                    .cells
+                    .ray(x, y, step)
+                    .map(|pos| self.cells[pos])
+                    .find(|&cell| cell != Cell::Floor);
+                first_seat == Some(Cell::OccupiedSeat)
+            })
+            .count()
     }
 
     fn get_number_of_direct_neighbors_occupied(&self, x: usize, y: usize) -> usize {
-        let mut counter = 0;
-
-        for &dy in &[-1, 0, 1] {
-            for &dx in &[-1, 0, 1] {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                let nx = x as isize + dx;
-                let ny = y as isize + dy;
-
-                if nx < 0 || nx >= self.width as isize || ny < 0 || ny >= self.height as isize {
-                    continue;
-                }
-
-                let nx = nx as usize;
-                let ny = ny as usize;
-
-                let cell = self.get_cell(nx, ny);
-                if let Cell::OccupiedSeat = cell {
-                    counter += 1;
-                }
-            }
-        }
-
-        counter
+        self.cells
+            .neighbors8(x, y)
+            .filter(|&pos| self.cells[pos] == Cell::OccupiedSeat)
+            .count()
     }
 
     fn next_step(&self, counter: fn(&Map, usize, usize) -> usize, threshold: usize) -> Map {
-        let mut new_map = Map {
-            cells: vec![Cell::Floor; self.width * self.height],
-            ..*self
-        };
-
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let near_count = counter(self, x, y);
-                let current_cell = self.get_cell(x, y);
-
-                let next_cell = match (current_cell, near_count) {
-                    (Cell::EmptySeat, 0) => Cell::OccupiedSeat,
-                    (Cell::OccupiedSeat, count) if count >= threshold => Cell::EmptySeat,
-                    (cell, _) => cell,
-                };
-
-                new_map.set_cell(x, y, next_cell);
+        let cells = Grid::from_fn(self.cells.width(), self.cells.height(), |x, y| {
+            let near_count = counter(self, x, y);
+            match (self.get_cell(x, y), near_count) {
+                (Cell::EmptySeat, 0) => Cell::OccupiedSeat,
+                (Cell::OccupiedSeat, count) if count >= threshold => Cell::EmptySeat,
+                (cell, _) => cell,
             }
-        }
-
-        new_map
+        });
+        Map { cells }
     }
 
-    fn next_step_part1(&self) -> Map {
+    pub fn next_step_part1(&self) -> Map {
         self.next_step(Map::get_number_of_direct_neighbors_occupied, 4)
     }
 
-    fn next_step_part2(&self) -> Map {
+    pub fn next_step_part2(&self) -> Map {
         self.next_step(Map::get_number_of_inline_neighbors_occupied, 5)
     }
 
-    fn count_occupied_stables(&self) -> usize {
+    pub fn count_occupied_stables(&self) -> usize {
         self.cells
             .iter()
             .filter(|&&cell| cell == Cell::OccupiedSeat)
             .count()
     }
 
-    fn step_until(&self, next_fn: fn(&Map) -> Map) -> Self {
+    /// Steps until the layout stops changing. Some layouts oscillate forever,
+    /// so this gives up when the time budget runs out.
+    fn step_until(&self, next_fn: fn(&Map) -> Map) -> AdventResult<Self> {
         let mut prec = self.clone();
         loop {
+            check_deadline()?;
             let current = next_fn(&prec);
 
             if current == prec {
-                return current;
+                return Ok(current);
             }
 
             prec = current;
         }
     }
 
-    fn step_until_stable_part1(&self) -> Self {
+    pub fn step_until_stable_part1(&self) -> AdventResult<Self> {
         self.step_until(Map::next_step_part1)
     }
 
-    fn step_until_stable_part2(&self) -> Self {
+    pub fn step_until_stable_part2(&self) -> AdventResult<Self> {
         self.step_until(Map::next_step_part2)
     }
 }
 
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let c = match self.get_cell(x, y) {
-                    Cell::Floor => '.',
-                    Cell::OccupiedSeat => '#',
-                    Cell::EmptySeat => 'L',
-                };
-                write!(f, "{}", c)?;
-            }
+        let rendered = self.cells.render(|cell| match cell {
+            Cell::Floor => '.',
+            Cell::OccupiedSeat => '#',
+            Cell::EmptySeat => 'L',
+        });
+        write!(f, "{}", rendered)
+    }
+}
 
-            writeln!(f)?;
-        }
-        Ok(())
+/// A square seat layout of about `size` cells, mostly empty seats.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let side = ((size as f64).sqrt() as usize).max(1);
+    let mut out = String::with_capacity((side + 1) * side);
+    for _ in 0..side {
+        out.extend((0..side).map(|_| if rng.chance(80) { 'L' } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+pub struct Day11;
+
+impl Solution for Day11 {
+    const DAY: u32 = 11;
+
+    type Input = Map;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        Map::parse(content)
+    }
+
+    fn part1(map: &Self::Input) -> AdventResult<usize> {
+        Ok(map.step_until_stable_part1()?.count_occupied_stables())
+    }
+
+    fn part2(map: &Self::Input) -> AdventResult<usize> {
+        Ok(map.step_until_stable_part2()?.count_occupied_stables())
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
     }
 }
 
-pub fn run(path: &str) {
-    let map = Map::read_from_path(path).expect("Cannot read map");
-    let stable1 = map.step_until_stable_part1();
-    println!("day11 part1: {}", stable1.count_occupied_stables());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+L.LL.LL.LL
+LLLLLLL.LL
+L.L.L..L..
+LLLL.LL.LL
+L.LL.LL.LL
+L.LLLLL.LL
+..L.L.....
+LLLLLLLLLL
+L.LLLLLL.L
+L.LLLLL.LL
+";
+
+    #[test]
+    fn example() {
+        let map = Map::parse(EXAMPLE).unwrap();
+        assert_eq!(Day11::part1(&map).unwrap(), 37);
+        assert_eq!(Day11::part2(&map).unwrap(), 26);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let map = Map::parse(EXAMPLE).unwrap();
+        assert_eq!(map.to_string(), EXAMPLE);
+    }
 
-    let stable2 = map.step_until_stable_part2();
-    println!("day11 part2: {}", stable2.count_occupied_stables());
+    #[test]
+    fn malformed_map() {
+        let err = expect_parse_error(Map::parse("L.L\nL?L\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(2)));
+
+        let err = expect_parse_error(Map::parse("L.L\nL.\n"));
+        assert_eq!(err.line, Some(2));
+    }
 }
@@ -1,206 +1,261 @@
-use crate::common::AdventResult;
+use crate::common::*;
+use crate::solution::Solution;
+use std::fmt::Write;
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-    Left,
-    Right,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Move(Compass),
+    Turn(Turn),
     Forward,
 }
 
-impl Direction {
-    fn get_offsets(self) -> (i32, i32) {
-        match self {
-            Direction::North => (0, -1),
-            Direction::South => (0, 1),
-            Direction::East => (1, 0),
-            Direction::West => (-1, 0),
-            _ => panic!("Can't compute offsets"),
-        }
-    }
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub action: Action,
+    pub value: i64,
+}
 
-    fn turn_left(self) -> Self {
-        match self {
-            Direction::North => Direction::West,
-            Direction::South => Direction::East,
-            Direction::East => Direction::North,
-            Direction::West => Direction::South,
-            _ => panic!("Can't turn left"),
-        }
-    }
+impl Instruction {
+    pub(crate) fn read_from_line(line: &str) -> AdventResult<Self> {
+        let first = line
+            .chars()
+            .next()
+            .ok_or_else(|| AdventError::parse("Empty instruction", line))?;
+        let (letter, count) = line.split_at(first.len_utf8());
 
-    fn turn_right(self) -> Self {
-        match self {
-            Direction::North => Direction::East,
-            Direction::South => Direction::West,
-            Direction::East => Direction::South,
-            Direction::West => Direction::North,
-            _ => panic!("Can't turn right"),
-        }
-    }
+        let action = match first {
+            'N' => Action::Move(Compass::North),
+            'S' => Action::Move(Compass::South),
+            'E' => Action::Move(Compass::East),
+            'W' => Action::Move(Compass::West),
+            'L' => Action::Turn(Turn::Left),
+            'R' => Action::Turn(Turn::Right),
+            'F' => Action::Forward,
+            _ => return Err(AdventError::parse_at("Unknown direction", line, letter)),
+        };
 
-    fn turn_repeat(self, turn_fn: fn(Self) -> Self, angle: i32) -> Self {
-        let count = angle / 90;
-        let mut res = self;
-        for _ in 0..count {
-            res = turn_fn(res);
+        let value = parse_token(line, count)?;
+        if let Action::Turn(_) = action {
+            if value < 0 || value % 90 != 0 {
+                return Err(AdventError::parse_at(
+                    "Turn angle must be a non-negative multiple of 90",
+                    line,
+                    count,
+                ));
+            }
         }
-        res
-    }
-
-    fn turn_left_angle(self, angle: i32) -> Self {
-        self.turn_repeat(Direction::turn_left, angle)
+        Ok(Instruction { action, value })
     }
 
-    fn turn_right_angle(self, angle: i32) -> Self {
-        self.turn_repeat(Direction::turn_right, angle)
+    /// Number of quarter turns of a turn instruction.
+    fn quarters(&self) -> u32 {
+        (self.value / 90 % 4) as u32
     }
 }
 
-#[derive(Debug, Clone)]
-struct Instruction {
-    direction: Direction,
-    value: i32,
+pub(crate) trait State {
+    fn new() -> Self;
+    fn apply(&mut self, instruction: &Instruction) -> AdventResult<()>;
+    fn manhattan_distance(&self) -> AdventResult<i64>;
 }
 
-impl Instruction {
-    fn read_from_line(line: &str) -> Self {
-        let direction = match line.chars().next().expect("Cannot read direction") {
-            'N' => Direction::North,
-            'S' => Direction::South,
-            'E' => Direction::East,
-            'W' => Direction::West,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            'F' => Direction::Forward,
-            _ => panic!("Unknown direction"),
-        };
+fn overflow(what: &str) -> AdventError {
+    AdventError::Overflow(what.to_owned())
+}
 
-        let value = line[1..].parse().expect("Cannot read count");
-        Instruction { direction, value }
-    }
+fn distance_from_origin(position: Point) -> AdventResult<i64> {
+    (position - Point::ORIGIN)
+        .checked_manhattan_len()
+        .ok_or_else(|| overflow("manhattan distance"))
 }
 
-trait State {
-    fn new() -> Self;
-    fn apply(&mut self, instruction: &Instruction);
-    fn manhattan_distance(&self) -> i32;
+/// `position` moved `times` times by `step`.
+fn advance(position: Point, step: Vec2, times: i64) -> AdventResult<Point> {
+    step.checked_mul(times)
+        .and_then(|offset| position.checked_add(offset))
+        .ok_or_else(|| overflow("ship position"))
 }
 
 #[derive(Debug, Clone, Copy)]
-struct State1 {
-    x: i32,
-    y: i32,
-    direction: Direction,
+pub(crate) struct State1 {
+    position: Point,
+    heading: Compass,
 }
 
 impl State for State1 {
     fn new() -> Self {
         State1 {
-            x: 0,
-            y: 0,
-            direction: Direction::East,
+            position: Point::ORIGIN,
+            heading: Compass::East,
         }
     }
 
-    fn apply(&mut self, instruction: &Instruction) {
-        let current_direction = match instruction.direction {
-            Direction::Left => {
-                self.direction = self.direction.turn_left_angle(instruction.value);
-                return;
-            }
-            Direction::Right => {
-                self.direction = self.direction.turn_right_angle(instruction.value);
-                return;
+    fn apply(&mut self, instruction: &Instruction) -> AdventResult<()> {
+        let direction = match instruction.action {
+            Action::Turn(turn) => {
+                self.heading = self.heading.turn(turn, instruction.quarters());
+                return Ok(());
             }
-            Direction::Forward => self.direction,
-            other => other,
+            Action::Forward => self.heading,
+            Action::Move(direction) => direction,
         };
 
-        let (dx, dy) = current_direction.get_offsets();
-        self.x += dx * instruction.value;
-        self.y += dy * instruction.value;
+        self.position = advance(self.position, direction.step(), instruction.value)?;
+        Ok(())
     }
 
-    fn manhattan_distance(&self) -> i32 {
-        self.x.abs() + self.y.abs()
+    fn manhattan_distance(&self) -> AdventResult<i64> {
+        distance_from_origin(self.position)
     }
 }
 
-fn part<S: State + std::fmt::Debug>(instructions: &[Instruction]) -> i32 {
+pub(crate) fn part<S: State + std::fmt::Debug>(instructions: &[Instruction]) -> AdventResult<i64> {
     let mut state = S::new();
     for inst in instructions {
-        state.apply(inst);
+        state.apply(inst)?;
     }
 
     state.manhattan_distance()
 }
 
 #[derive(Debug, Clone, Copy)]
-struct State2 {
-    boat_x: i32,
-    boat_y: i32,
-    way_dx: i32,
-    way_dy: i32,
-    direction: Direction,
+pub(crate) struct State2 {
+    boat: Point,
+    waypoint: Vec2,
 }
 
 impl State for State2 {
     fn new() -> Self {
         State2 {
-            boat_x: 0,
-            boat_y: 0,
-            way_dx: 10,
-            way_dy: -1,
-            direction: Direction::East,
+            boat: Point::ORIGIN,
+            waypoint: Vec2::new(10, -1),
         }
     }
 
-    fn apply(&mut self, instruction: &Instruction) {
-        match instruction.direction {
-            Direction::North | Direction::South | Direction::East | Direction::West => {
-                let (dx, dy) = instruction.direction.get_offsets();
-                self.way_dx += dx * instruction.value;
-                self.way_dy += dy * instruction.value
-            }
-            Direction::Left => {
-                let count = instruction.value / 90;
-                for _ in 0..count {
-                    let (nx, ny) = (self.way_dy, -self.way_dx);
-                    self.way_dx = nx;
-                    self.way_dy = ny;
-                }
+    fn apply(&mut self, instruction: &Instruction) -> AdventResult<()> {
+        match instruction.action {
+            Action::Move(direction) => {
+                self.waypoint = direction
+                    .step()
+                    .checked_mul(instruction.value)
+                    .and_then(|offset| self.waypoint.checked_add(offset))
+                    .ok_or_else(|| overflow("waypoint"))?;
             }
-            Direction::Right => {
-                let count = instruction.value / 90;
-                for _ in 0..count {
-                    let (nx, ny) = (-self.way_dy, self.way_dx);
-                    self.way_dx = nx;
-                    self.way_dy = ny;
-                }
-            }
-            Direction::Forward => {
-                self.boat_x += self.way_dx * instruction.value;
-                self.boat_y += self.way_dy * instruction.value;
+            Action::Turn(turn) => {
+                self.waypoint = self
+                    .waypoint
+                    .checked_rotate(turn, instruction.quarters())
+                    .ok_or_else(|| overflow("waypoint"))?;
             }
+            Action::Forward => self.boat = advance(self.boat, self.waypoint, instruction.value)?,
         }
+        Ok(())
     }
 
-    fn manhattan_distance(&self) -> i32 {
-        self.boat_x.abs() + self.boat_y.abs()
+    fn manhattan_distance(&self) -> AdventResult<i64> {
+        distance_from_origin(self.boat)
     }
 }
 
-fn read_instructions(path: &str) -> AdventResult<Vec<Instruction>> {
-    let content = std::fs::read_to_string(path)?;
-    Ok(content.lines().map(Instruction::read_from_line).collect())
+pub fn parse_instructions(content: &str) -> AdventResult<Vec<Instruction>> {
+    parse_lines_with(content, Instruction::read_from_line)
 }
 
-pub fn run(path: &str) {
-    let instructions = read_instructions(path).expect("Cannot read instructions");
-    println!("day12 part1: {}", part::<State1>(&instructions));
-    println!("day12 part2: {}", part::<State2>(&instructions));
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        let roll = rng.range(0, 100);
+        if roll < 15 {
+            let turn = rng.choose(&['L', 'R']);
+            writeln!(out, "{}{}", turn, 90 * rng.range(1, 4)).unwrap();
+        } else {
+            let action = if roll < 60 {
+                *rng.choose(&['N', 'S', 'E', 'W'])
+            } else {
+                'F'
+            };
+            writeln!(out, "{}{}", action, rng.range(1, 101)).unwrap();
+        }
+    }
+    out
+}
+
+pub struct Day12;
+
+impl Solution for Day12 {
+    const DAY: u32 = 12;
+
+    type Input = Vec<Instruction>;
+    type Answer1 = i64;
+    type Answer2 = i64;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_instructions(content)
+    }
+
+    fn part1(instructions: &Self::Input) -> AdventResult<i64> {
+        part::<State1>(instructions)
+    }
+
+    fn part2(instructions: &Self::Input) -> AdventResult<i64> {
+        part::<State2>(instructions)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+F10
+N3
+F7
+R90
+F11
+";
+
+    #[test]
+    fn example() {
+        let instructions = parse_instructions(EXAMPLE).unwrap();
+        assert_eq!(Day12::part1(&instructions).unwrap(), 25);
+        assert_eq!(Day12::part2(&instructions).unwrap(), 286);
+    }
+
+    #[test]
+    fn far_away() {
+        let instructions = parse_instructions("F9223372036854775807\n").unwrap();
+        assert_eq!(Day12::part1(&instructions).unwrap(), i64::MAX);
+        assert!(matches!(
+            Day12::part2(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
+
+        let instructions = parse_instructions("N9223372036854775807\nE1\n").unwrap();
+        assert!(matches!(
+            Day12::part1(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
+
+        let instructions = parse_instructions("W9223372036854775807\nW11\nL90\n").unwrap();
+        assert!(matches!(
+            Day12::part2(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_instruction() {
+        let err = expect_parse_error(parse_instructions("F10\nX3\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(1)));
+
+        let err = expect_parse_error(Instruction::read_from_line("R45"));
+        assert_eq!(err.column, Some(2));
+
+        assert!(Instruction::read_from_line("").is_err());
+        assert!(Instruction::read_from_line("é1").is_err());
+    }
 }
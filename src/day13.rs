@@ -1,8 +1,7 @@
-use core::panic;
+use crate::common::*;
+use crate::solution::Solution;
 
-use crate::common::AdventResult;
-
-fn mod_inv(a: i64, module: i64) -> i64 {
+pub(crate) fn mod_inv(a: i64, module: i64) -> i64 {
     let mut mn = (module, a);
     let mut xy = (0, 1);
 
@@ -17,58 +16,69 @@ fn mod_inv(a: i64, module: i64) -> i64 {
     xy.0
 }
 
-fn main() {
-    println!("{}", mod_inv(42, 2017))
-}
-
 #[derive(Debug, Clone)]
-struct PuzzleInput {
-    start_timestamp: u32,
-    bus_ids: Vec<Option<u32>>,
+pub struct PuzzleInput {
+    pub start_timestamp: u32,
+    pub bus_ids: Vec<Option<u32>>,
 }
 
 impl PuzzleInput {
-    fn find_first_bus(&self) -> Option<(u32, u32)> {
-        for ts in self.start_timestamp.. {
-            for bus_id in &self.bus_ids {
-                if let Some(bus_id) = bus_id {
-                    if ts % bus_id == 0 {
-                        return Some((*bus_id, ts - self.start_timestamp));
-                    }
+    /// First bus leaving at or after `start_timestamp`, with the wait.
+    pub(crate) fn find_first_bus(&self) -> AdventResult<(u32, u32)> {
+        if self.bus_ids.iter().all(Option::is_none) {
+            return Err(AdventError::NoSolution);
+        }
+
+        for ts in self.start_timestamp..=u32::MAX {
+            checkpoint()?;
+            for bus_id in self.bus_ids.iter().flatten() {
+                if ts % bus_id == 0 {
+                    return Ok((*bus_id, ts - self.start_timestamp));
                 }
             }
         }
-        None
+        Err(AdventError::NoSolution)
     }
 
+    #[cfg(test)]
     fn is_valid_ts_part1(&self, ts: u32) -> bool {
-        for (current_ts, bus_id) in (ts..).into_iter().zip(self.bus_ids.iter()) {
+        for (offset, bus_id) in self.bus_ids.iter().enumerate() {
             if let Some(bus_id) = bus_id {
-                if current_ts % bus_id != 0 {
-                    return false;
+                match ts.checked_add(offset as u32) {
+                    Some(current_ts) if current_ts % bus_id == 0 => {}
+                    _ => return false,
                 }
             }
         }
-        return true;
+        true
     }
 }
 
-fn read_input(path: &str) -> AdventResult<PuzzleInput> {
-    let content = std::fs::read_to_string(path)?;
+pub fn parse_input(content: &str) -> AdventResult<PuzzleInput> {
     let mut lines_iter = content.lines();
 
-    let first_line = lines_iter.next().unwrap();
-    let start_timestamp = first_line.parse()?;
+    let first_line = lines_iter
+        .next()
+        .ok_or_else(|| AdventError::parse("Missing timestamp line", "").at_line(1))?;
+    let start_timestamp = parse_token(first_line, first_line).map_err(|err| err.at_line(1))?;
 
-    let second_line = lines_iter.next().unwrap();
+    let second_line = lines_iter
+        .next()
+        .ok_or_else(|| AdventError::parse("Missing bus ids line", "").at_line(2))?;
 
     let mut bus_ids = Vec::new();
-    for part in second_line.split(",") {
-        if let Ok(id) = part.parse() {
-            bus_ids.push(Some(id));
-        } else {
+    for part in second_line.split(',') {
+        if part == "x" {
             bus_ids.push(None);
+            continue;
         }
+
+        let id: u32 = parse_token(second_line, part).map_err(|err| err.at_line(2))?;
+        if id == 0 {
+            let err = AdventError::parse_at("Bus id must be positive", second_line, part);
+            return Err(err.at_line(2));
+        }
+        bus_ids.push(Some(id));
     }
 
     Ok(PuzzleInput {
@@ -77,37 +87,39 @@ fn read_input(path: &str) -> AdventResult<PuzzleInput> {
     })
 }
 
-fn part1(puzzle_input: &PuzzleInput) -> u32 {
-    let (bus_id, ts) = puzzle_input
-        .find_first_bus()
-        .expect("Cannot find first bus");
-    bus_id * ts
+pub(crate) fn part1(puzzle_input: &PuzzleInput) -> AdventResult<u32> {
+    let (bus_id, ts) = puzzle_input.find_first_bus()?;
+    bus_id
+        .checked_mul(ts)
+        .ok_or_else(|| AdventError::Overflow("bus id times wait".to_owned()))
 }
 
-fn part2_raw(puzzle_input: &PuzzleInput) -> u32 {
+#[cfg(test)]
+pub(crate) fn part2_raw(puzzle_input: &PuzzleInput) -> AdventResult<u32> {
     let first_id = puzzle_input
         .bus_ids
         .iter()
         .filter_map(|id| *id)
         .next()
-        .expect("No first id");
+        .ok_or(AdventError::NoSolution)?;
 
-    for coeff in 0.. {
+    for coeff in 0..=(u32::MAX / first_id) {
+        checkpoint()?;
         let ts = first_id * coeff;
         if puzzle_input.is_valid_ts_part1(ts) {
-            return ts;
+            return Ok(ts);
         }
     }
-    panic!("No ts found");
+    Err(AdventError::NoSolution)
 }
 
-fn part2(puzzle_input: &PuzzleInput) -> i64 {
+pub(crate) fn part2(puzzle_input: &PuzzleInput) -> AdventResult<i64> {
     // https://fr.wikipedia.org/wiki/Th%C3%A9or%C3%A8me_des_restes_chinois
     let mut nis = Vec::new();
     let mut ais = Vec::new();
     for (offset, bus_id) in puzzle_input.bus_ids.iter().enumerate() {
         let offset = offset as u64;
-        if let Some(bus_id) = bus_id.clone() {
+        if let Some(bus_id) = *bus_id {
             let mut ai = -(offset as i64);
             while ai < 0 {
                 ai += bus_id as i64;
@@ -119,36 +131,170 @@ fn part2(puzzle_input: &PuzzleInput) -> i64 {
     }
 
     assert_eq!(nis.len(), ais.len());
+    if nis.is_empty() {
+        return Err(AdventError::NoSolution);
+    }
+
+    let overflow = || AdventError::Overflow("bus ids product".to_owned());
 
     let mut eis = Vec::new();
 
     for (i, &ni) in nis.iter().enumerate() {
-        let mut ni_chap = 1;
+        let mut ni_chap: i64 = 1;
         for (j, &nj) in nis.iter().enumerate() {
             if j != i {
-                ni_chap *= nj;
+                ni_chap = ni_chap.checked_mul(nj).ok_or_else(overflow)?;
             }
         }
 
         let vi = mod_inv(ni_chap, ni);
-        let ei = vi * ni_chap;
+        let ei = vi.checked_mul(ni_chap).ok_or_else(overflow)?;
         eis.push(ei);
     }
 
-    let n: i64 = nis.iter().product();
+    let n = nis
+        .iter()
+        .try_fold(1i64, |acc, &ni| acc.checked_mul(ni))
+        .ok_or_else(overflow)?;
 
     assert_eq!(eis.len(), ais.len());
-    let x: i64 = eis
+    let x: i128 = eis
         .into_iter()
-        .zip(ais.into_iter())
-        .map(|(ei, ai)| ei * ai)
-        .sum();
+        .zip(ais)
+        .fold(0, |x, (ei, ai)| (x + ei as i128 * ai as i128) % n as i128);
+
+    Ok(x as i64)
+}
+
+/// Primes are pairwise coprime, which part 2 relies on.
+fn small_primes(below: u64) -> Vec<u64> {
+    (2..below)
+        .filter(|&n| (2..).take_while(|d| d * d <= n).all(|d| n % d != 0))
+        .collect()
+}
+
+/// A schedule of `size` slots holding a few prime bus ids, the first slot
+/// always being a bus. Their product stays below 2^50 so part 2 fits in an `i64`.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let slots = size.max(1);
+    let mut primes = small_primes(1000);
+    primes.retain(|&p| p >= 7);
+    rng.shuffle(&mut primes);
+
+    let mut schedule = vec![None; slots];
+    let mut product: u64 = 1;
+    let mut free_slots: Vec<usize> = (1..slots).collect();
+    rng.shuffle(&mut free_slots);
+    let mut slot_iter = std::iter::once(0).chain(free_slots);
+
+    for prime in primes {
+        if product * prime >= 1 << 50 {
+            break;
+        }
+        match slot_iter.next() {
+            Some(slot) => schedule[slot] = Some(prime),
+            None => break,
+        }
+        product *= prime;
+    }
+
+    let bus_ids: Vec<String> = schedule
+        .iter()
+        .map(|id| id.map_or_else(|| "x".to_owned(), |id| id.to_string()))
+        .collect();
+    format!(
+        "{}\n{}\n",
+        rng.range(100_000, 10_000_000),
+        bus_ids.join(",")
+    )
+}
 
-    x % n
+pub struct Day13;
+
+impl Solution for Day13 {
+    const DAY: u32 = 13;
+
+    type Input = PuzzleInput;
+    type Answer1 = u32;
+    type Answer2 = i64;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_input(content)
+    }
+
+    fn part1(puzzle_input: &Self::Input) -> AdventResult<u32> {
+        part1(puzzle_input)
+    }
+
+    fn part2(puzzle_input: &Self::Input) -> AdventResult<i64> {
+        part2(puzzle_input)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let puzzle_input = read_input(path).expect("Cannot read puzzle input");
-    println!("day13 part1: {}", part1(&puzzle_input));
-    println!("day13 part2: {}", part2(&puzzle_input));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+939
+7,13,x,x,59,x,31,19
+";
+
+    #[test]
+    fn example() {
+        let puzzle_input = parse_input(EXAMPLE).unwrap();
+        assert_eq!(Day13::part1(&puzzle_input).unwrap(), 295);
+        assert_eq!(Day13::part2(&puzzle_input).unwrap(), 1068781);
+    }
+
+    #[test]
+    fn part2_examples() {
+        let examples = [
+            ("17,x,13,19", 3417),
+            ("67,7,59,61", 754018),
+            ("67,x,7,59,61", 779210),
+            ("67,7,x,59,61", 1261476),
+            ("1789,37,47,1889", 1202161486),
+        ];
+        for &(bus_ids, expected) in &examples {
+            let puzzle_input = parse_input(&format!("0\n{}\n", bus_ids)).unwrap();
+            assert_eq!(part2(&puzzle_input).unwrap(), expected);
+            assert_eq!(part2_raw(&puzzle_input).unwrap() as i64, expected);
+        }
+    }
+
+    #[test]
+    fn runaway_inputs() {
+        let no_bus = parse_input("939\nx,x\n").unwrap();
+        assert!(matches!(part1(&no_bus), Err(AdventError::NoSolution)));
+
+        let late = parse_input("1\n100000\n").unwrap();
+        assert!(matches!(part1(&late), Err(AdventError::Overflow(_))));
+
+        let no_bus = parse_input("0\nx,x\n").unwrap();
+        assert!(matches!(part2(&no_bus), Err(AdventError::NoSolution)));
+        assert!(matches!(part2_raw(&no_bus), Err(AdventError::NoSolution)));
+
+        // No timestamp below u32::MAX fits: the scan would take minutes.
+        let budget = Some(std::time::Duration::from_millis(50));
+        let primes = parse_input("0\n2,3,5,7,11,13,17,19,23,29,31\n").unwrap();
+        let result = with_budget(budget, || part2_raw(&primes));
+        assert!(matches!(result, Err(AdventError::Timeout(_))));
+    }
+
+    #[test]
+    fn malformed_input() {
+        let err = expect_parse_error(parse_input("939\n"));
+        assert_eq!(err.line, Some(2));
+
+        let err = expect_parse_error(parse_input("939\n7,y,13\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(3)));
+
+        let err = expect_parse_error(parse_input("939\n7,0\n"));
+        assert_eq!(err.text, "0");
+    }
 }
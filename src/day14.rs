@@ -1,13 +1,15 @@
-use crate::common::AdventResult;
+use crate::common::*;
+use crate::solution::Solution;
 use std::collections::HashMap;
+use std::fmt::Write;
 
 #[derive(Debug, Clone)]
-enum Instruction {
+pub enum Instruction {
     Mask(Vec<u8>),
     Set { address: u64, value: u64 },
 }
 
-fn combine_with_mask(mask: &[u8], value: u64) -> u64 {
+pub(crate) fn combine_with_mask(mask: &[u8], value: u64) -> u64 {
     let mut res = value;
     for (i, mask_bit) in mask.iter().rev().enumerate() {
         assert!(i < 36);
@@ -25,7 +27,7 @@ fn combine_with_mask(mask: &[u8], value: u64) -> u64 {
     res
 }
 
-fn combine_with_mask_v2(mask: &[u8], value: u64) -> Vec<u64> {
+pub(crate) fn combine_with_mask_v2(mask: &[u8], value: u64) -> Vec<u64> {
     let mut res = vec![value];
     for (i, mask_bit) in mask.iter().rev().enumerate() {
         assert!(i < 36);
@@ -46,40 +48,45 @@ fn combine_with_mask_v2(mask: &[u8], value: u64) -> Vec<u64> {
     res
 }
 
-fn read_instructions(path: &str) -> AdventResult<Vec<Instruction>> {
-    let content = std::fs::read_to_string(path)?;
-    let mut instructions = Vec::new();
-    for line in content.lines() {
-        let inst = if line.starts_with("mask") {
-            let mask = line.trim_start_matches("mask = ").trim_end().to_owned();
-            let mask = mask.bytes().collect();
-            Instruction::Mask(mask)
-        } else {
-            let parts: Vec<_> = line.split(" = ").collect();
-            let start = parts[0];
-            let address = start
-                .trim_start_matches("mem[")
-                .trim_end_matches("]")
-                .parse()?;
-
-            let value = parts[1].parse()?;
-
-            Instruction::Set { address, value }
-        };
-
-        instructions.push(inst);
-    }
-    Ok(instructions)
+fn parse_instruction(line: &str) -> AdventResult<Instruction> {
+    if line.starts_with("mask") {
+        let mask = line.trim_start_matches("mask = ").trim_end();
+        if mask.len() != 36 {
+            return Err(AdventError::parse_at("Expected a 36 bits mask", line, mask));
+        }
+        if let Some((offset, c)) = mask
+            .char_indices()
+            .find(|&(_, c)| !matches!(c, '0' | '1' | 'X'))
+        {
+            let bit = &mask[offset..offset + c.len_utf8()];
+            return Err(AdventError::parse_at("Unknown mask bit", line, bit));
+        }
+        Ok(Instruction::Mask(mask.bytes().collect()))
+    } else {
+        let (target, value) = split_pair(line, line, " = ")?;
+        let address_part = target
+            .strip_prefix("mem[")
+            .and_then(|target| target.strip_suffix(']'))
+            .ok_or_else(|| AdventError::parse_at("Expected 'mem[<address>]'", line, target))?;
+        let address = parse_token(line, address_part)?;
+        let value = parse_token(line, value)?;
+
+        Ok(Instruction::Set { address, value })
+    }
+}
+
+pub fn parse_instructions(content: &str) -> AdventResult<Vec<Instruction>> {
+    parse_lines_with(content, parse_instruction)
 }
 
 #[derive(Debug, Default)]
-struct Memory {
+pub struct Memory {
     mem: HashMap<u64, u64>,
     mask: Vec<u8>,
 }
 
 impl Memory {
-    fn run_inst(&mut self, instruction: &Instruction) {
+    pub fn run_inst(&mut self, instruction: &Instruction) {
         match instruction {
             Instruction::Mask(mask) => {
                 self.mask = mask.clone();
@@ -91,7 +98,7 @@ impl Memory {
         }
     }
 
-    fn run_inst_v2(&mut self, instruction: &Instruction) {
+    pub fn run_inst_v2(&mut self, instruction: &Instruction) {
         match instruction {
             Instruction::Mask(mask) => {
                 self.mask = mask.clone();
@@ -105,12 +112,15 @@ impl Memory {
         }
     }
 
-    fn sum_values(&self) -> u64 {
-        self.mem.values().sum()
+    pub fn sum_values(&self) -> AdventResult<u64> {
+        self.mem
+            .values()
+            .try_fold(0u64, |sum, &value| sum.checked_add(value))
+            .ok_or_else(|| AdventError::Overflow("sum of memory values".to_owned()))
     }
 }
 
-fn part1(instructions: &[Instruction]) -> u64 {
+pub(crate) fn part1(instructions: &[Instruction]) -> AdventResult<u64> {
     let mut mem = Memory::default();
 
     for inst in instructions {
@@ -119,7 +129,7 @@ fn part1(instructions: &[Instruction]) -> u64 {
     mem.sum_values()
 }
 
-fn part2(instructions: &[Instruction]) -> u64 {
+pub(crate) fn part2(instructions: &[Instruction]) -> AdventResult<u64> {
     let mut mem = Memory::default();
 
     for inst in instructions {
@@ -128,8 +138,114 @@ fn part2(instructions: &[Instruction]) -> u64 {
     mem.sum_values()
 }
 
-pub fn run(path: &str) {
-    let instructions = read_instructions(path).expect("Cannot read instructions");
-    println!("day14 part1: {}", part1(&instructions));
-    println!("day14 part2: {}", part2(&instructions));
+/// A program of `size` lines: masks with at most nine floating bits, each
+/// followed by a few writes.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    let mut lines = 0;
+    while lines < size.max(1) {
+        let mut mask: Vec<char> = (0..36)
+            .map(|_| if rng.chance(50) { '1' } else { '0' })
+            .collect();
+        for _ in 0..rng.range(0, 10) {
+            mask[rng.index(36)] = 'X';
+        }
+        writeln!(out, "mask = {}", mask.iter().collect::<String>()).unwrap();
+        lines += 1;
+
+        for _ in 0..rng.range(1, 7) {
+            let address = rng.range(0, 1 << 16);
+            let value = rng.range(0, 1 << 36);
+            writeln!(out, "mem[{}] = {}", address, value).unwrap();
+            lines += 1;
+        }
+    }
+    out
+}
+
+pub struct Day14;
+
+impl Solution for Day14 {
+    const DAY: u32 = 14;
+
+    type Input = Vec<Instruction>;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_instructions(content)
+    }
+
+    fn part1(instructions: &Self::Input) -> AdventResult<u64> {
+        part1(instructions)
+    }
+
+    fn part2(instructions: &Self::Input) -> AdventResult<u64> {
+        part2(instructions)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X
+mem[8] = 11
+mem[7] = 101
+mem[8] = 0
+";
+
+    const EXAMPLE_PART2: &str = "\
+mask = 000000000000000000000000000000X1001X
+mem[42] = 100
+mask = 00000000000000000000000000000000X0XX
+mem[26] = 1
+";
+
+    #[test]
+    fn example() {
+        let instructions = parse_instructions(EXAMPLE).unwrap();
+        assert_eq!(Day14::part1(&instructions).unwrap(), 165);
+
+        let instructions = parse_instructions(EXAMPLE_PART2).unwrap();
+        assert_eq!(Day14::part2(&instructions).unwrap(), 208);
+    }
+
+    #[test]
+    fn huge_sum() {
+        let instructions =
+            parse_instructions("mem[0] = 18446744073709551615\nmem[1] = 1\n").unwrap();
+        assert!(matches!(
+            Day14::part1(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
+        assert!(matches!(
+            Day14::part2(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_instruction() {
+        let err = expect_parse_error(parse_instructions(
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X\nmem[8 = 11\n",
+        ));
+        assert_eq!(err.line, Some(2));
+
+        let err = expect_parse_error(parse_instruction("mask = XX01"));
+        assert_eq!(err.column, Some(8));
+
+        let err = expect_parse_error(parse_instruction(
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX2X",
+        ));
+        assert_eq!(err.column, Some(42));
+
+        let err = expect_parse_error(parse_instruction("mem[a] = 11"));
+        assert_eq!(err.column, Some(5));
+    }
 }
@@ -1,69 +1,427 @@
 use crate::common::*;
+use crate::report::csv_field;
+use crate::solution::Solution;
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::fmt::{self, Write};
 
 lazy_static! {
-    pub static ref LINE_RE: Regex =
-        Regex::new(r"(?P<min>\d+)-(?P<max>\d+)\s(?P<letter>\w):\s(?P<password>\w+)").unwrap();
+    pub(crate) static ref LINE_RE: Regex =
+        Regex::new(r"^(?P<min>\d+)-(?P<max>\d+) (?P<letter>\w): (?P<password>\w+)$").unwrap();
 }
 
 #[derive(Debug, Clone)]
-struct PasswordLine {
-    min: u32,
-    max: u32,
-    letter: char,
-    password: String,
+pub struct PasswordLine {
+    pub min: u32,
+    pub max: u32,
+    pub letter: char,
+    pub password: String,
 }
 
 impl PasswordLine {
-    fn is_valid_part1(&self) -> bool {
-        let mut counter = 0;
-        for c in self.password.chars() {
-            if c == self.letter {
-                counter += 1;
-            }
-        }
-        self.min <= counter && counter <= self.max
+    /// How many times the letter appears in the password.
+    pub fn letter_count(&self) -> u32 {
+        self.password.chars().filter(|&c| c == self.letter).count() as u32
+    }
+
+    /// Whether the letter is at the 1-based `position` of the password.
+    pub fn letter_at(&self, position: u32) -> bool {
+        position
+            .checked_sub(1)
+            .and_then(|index| self.password.chars().nth(index as usize))
+            == Some(self.letter)
+    }
+}
+
+/// A rule each `PasswordLine` of the database can be audited against. The
+/// two numbers and the letter of a line are the parameters of the rule.
+pub trait PasswordPolicy {
+    /// The spec `policy_by_name` builds this policy from.
+    fn name(&self) -> String;
+
+    /// Why `line` breaks the policy, `None` when it complies.
+    fn violation(&self, line: &PasswordLine) -> Option<String>;
+
+    fn is_valid(&self, line: &PasswordLine) -> bool {
+        self.violation(line).is_none()
     }
+}
+
+/// The letter appears between `min` and `max` times (the sled rental rule).
+pub(crate) struct CountRange;
 
-    fn is_valid_part2(&self) -> bool {
-        let chars: Vec<char> = self.password.chars().collect();
+impl PasswordPolicy for CountRange {
+    fn name(&self) -> String {
+        "count".to_owned()
+    }
 
-        let is_min = chars[self.min as usize - 1] == self.letter;
-        let is_max = chars[self.max as usize - 1] == self.letter;
-        is_min ^ is_max
+    fn violation(&self, line: &PasswordLine) -> Option<String> {
+        let count = line.letter_count();
+        let (bound, limit) = if count < line.min {
+            ("min", line.min)
+        } else if count > line.max {
+            ("max", line.max)
+        } else {
+            return None;
+        };
+        Some(format!(
+            "letter '{}' appears {} times, {} {}",
+            line.letter, count, bound, limit
+        ))
     }
 }
 
-fn read_password_line(line: &str) -> Option<PasswordLine> {
-    if let Some(captures) = LINE_RE.captures(line) {
-        Some(PasswordLine {
-            min: captures["min"].parse().unwrap(),
-            max: captures["max"].parse().unwrap(),
-            letter: captures["letter"].chars().next().unwrap(),
-            password: captures["password"].into(),
+/// How `Positions` combines the letter being at `min` and at `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Combine {
+    Xor,
+    And,
+    Or,
+}
+
+/// The letter is at the 1-based positions `min` and `max`, combined with
+/// `Combine` (the toboggan rule is `Xor`).
+pub(crate) struct Positions(pub Combine);
+
+impl PasswordPolicy for Positions {
+    fn name(&self) -> String {
+        match self.0 {
+            Combine::Xor => "xor",
+            Combine::And => "and",
+            Combine::Or => "or",
+        }
+        .to_owned()
+    }
+
+    fn violation(&self, line: &PasswordLine) -> Option<String> {
+        let (first, second) = (line.letter_at(line.min), line.letter_at(line.max));
+        let valid = match self.0 {
+            Combine::Xor => first ^ second,
+            Combine::And => first && second,
+            Combine::Or => first || second,
+        };
+        if valid {
+            return None;
+        }
+
+        let (min, max, letter) = (line.min, line.max, line.letter);
+        Some(match (first, second) {
+            (true, true) => format!("both positions {} and {} are '{}'", min, max, letter),
+            (false, false) => format!("neither position {} nor {} is '{}'", min, max, letter),
+            (true, false) => format!("position {} is not '{}'", max, letter),
+            (false, true) => format!("position {} is not '{}'", min, letter),
         })
-    } else {
-        None
     }
 }
 
-fn read_password_file(path: &str) -> AdventResult<Vec<PasswordLine>> {
-    let content = std::fs::read_to_string(path)?;
-    let lines = content
+/// None of `letters` appears in the password.
+pub(crate) struct ForbiddenLetters(pub String);
+
+impl PasswordPolicy for ForbiddenLetters {
+    fn name(&self) -> String {
+        format!("forbidden={}", self.0)
+    }
+
+    fn violation(&self, line: &PasswordLine) -> Option<String> {
+        line.password
+            .chars()
+            .find(|&c| self.0.contains(c))
+            .map(|c| format!("contains forbidden letter '{}'", c))
+    }
+}
+
+/// The password matches a regex.
+pub(crate) struct MatchesRegex(pub Regex);
+
+impl PasswordPolicy for MatchesRegex {
+    fn name(&self) -> String {
+        format!("regex={}", self.0)
+    }
+
+    fn violation(&self, line: &PasswordLine) -> Option<String> {
+        if self.0.is_match(&line.password) {
+            None
+        } else {
+            Some(format!("does not match {}", self.0))
+        }
+    }
+}
+
+pub const POLICY_NAMES: &str = "count, xor, and, or, forbidden=<letters>, regex=<pattern>";
+
+/// Builds a policy from its name, one of `POLICY_NAMES`.
+pub fn policy_by_name(spec: &str) -> AdventResult<Box<dyn PasswordPolicy>> {
+    let (name, argument) = match spec.split_once('=') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (spec, None),
+    };
+    let policy: Box<dyn PasswordPolicy> = match (name, argument) {
+        ("count", None) => Box::new(CountRange),
+        ("xor", None) => Box::new(Positions(Combine::Xor)),
+        ("and", None) => Box::new(Positions(Combine::And)),
+        ("or", None) => Box::new(Positions(Combine::Or)),
+        ("forbidden", Some(letters)) if !letters.is_empty() => {
+            Box::new(ForbiddenLetters(letters.to_owned()))
+        }
+        ("regex", Some(pattern)) => {
+            let regex = Regex::new(pattern).map_err(|err| {
+                AdventError::parse_at(format!("Invalid regex ({})", err), spec, pattern)
+            })?;
+            Box::new(MatchesRegex(regex))
+        }
+        _ => {
+            let message = format!("Unknown password policy, expected one of {}", POLICY_NAMES);
+            return Err(AdventError::parse(message, spec));
+        }
+    };
+    Ok(policy)
+}
+
+pub fn count_valid(rows: &[PasswordLine], policy: &dyn PasswordPolicy) -> usize {
+    rows.iter().filter(|row| policy.is_valid(row)).count()
+}
+
+pub(crate) fn read_password_line(line: &str) -> AdventResult<PasswordLine> {
+    let captures = capture(&LINE_RE, line, "Invalid password line")?;
+    Ok(PasswordLine {
+        min: captures.parse("min")?,
+        max: captures.parse("max")?,
+        letter: captures.char("letter")?,
+        password: captures.str("password")?.to_owned(),
+    })
+}
+
+pub fn parse_password_file(content: &str) -> AdventResult<Vec<PasswordLine>> {
+    parse_lines_with(content, read_password_line)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Valid,
+    Invalid,
+    Malformed,
+}
+
+impl Outcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Valid => "valid",
+            Outcome::Invalid => "invalid",
+            Outcome::Malformed => "malformed",
+        }
+    }
+}
+
+/// The audit of one line of the database against a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// 1-based line number.
+    pub line: usize,
+    pub outcome: Outcome,
+    /// Why the line is invalid or malformed.
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.outcome.name())?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every line of `content` against `policy`. Unlike
+/// `parse_password_file`, a malformed line does not stop the audit: it is
+/// reported with the parse error as its reason.
+pub fn audit(content: &str, policy: &dyn PasswordPolicy) -> Vec<AuditEntry> {
+    content
         .lines()
-        .map(read_password_line)
-        .map(Option::unwrap)
-        .collect();
-    Ok(lines)
+        .enumerate()
+        .map(|(index, text)| {
+            let (outcome, reason) = match read_password_line(text) {
+                Ok(row) => match policy.violation(&row) {
+                    None => (Outcome::Valid, None),
+                    Some(reason) => (Outcome::Invalid, Some(reason)),
+                },
+                Err(err) => (Outcome::Malformed, Some(err.to_string())),
+            };
+            AuditEntry {
+                line: index + 1,
+                outcome,
+                reason,
+            }
+        })
+        .collect()
+}
+
+/// Audits as CSV, one row per line and policy, with a header.
+pub fn audit_to_csv(audits: &[(String, Vec<AuditEntry>)]) -> String {
+    let mut out = String::from("policy,line,outcome,reason\n");
+    for (policy, entries) in audits {
+        for entry in entries {
+            writeln!(
+                out,
+                "{},{},{},{}",
+                csv_field(policy),
+                entry.line,
+                entry.outcome.name(),
+                csv_field(entry.reason.as_deref().unwrap_or_default())
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        let len = rng.range(4, 21);
+        let password: String = (0..len)
+            .map(|_| (b'a' + rng.range(0, 6) as u8) as char)
+            .collect();
+        let letter = (b'a' + rng.range(0, 6) as u8) as char;
+        let min = rng.range(1, len / 2 + 1);
+        let max = rng.range(min + 1, len + 1);
+        writeln!(out, "{}-{} {}: {}", min, max, letter, password).unwrap();
+    }
+    out
+}
+
+pub struct Day2;
+
+impl Solution for Day2 {
+    const DAY: u32 = 2;
+
+    type Input = Vec<PasswordLine>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_password_file(content)
+    }
+
+    fn part1(rows: &Self::Input) -> AdventResult<usize> {
+        Ok(count_valid(rows, &CountRange))
+    }
+
+    fn part2(rows: &Self::Input) -> AdventResult<usize> {
+        Ok(count_valid(rows, &Positions(Combine::Xor)))
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let rows = read_password_file(path).expect("Cannot read input file");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+1-3 a: abcde
+1-3 b: cdefg
+2-9 c: ccccccccc
+";
 
-    let part1 = rows.iter().filter(|pl| pl.is_valid_part1()).count();
-    println!("day2 part1: {}", part1);
+    #[test]
+    fn example() {
+        let rows = parse_password_file(EXAMPLE).unwrap();
+        assert_eq!(Day2::part1(&rows).unwrap(), 2);
+        assert_eq!(Day2::part2(&rows).unwrap(), 1);
+    }
+
+    #[test]
+    fn malformed_line() {
+        let err = expect_parse_error(parse_password_file("1-3 a: abcde\n1 a: abcde\n"));
+        assert_eq!(err.line, Some(2));
 
-    let part2 = rows.iter().filter(|pl| pl.is_valid_part2()).count();
-    println!("day2 part2: {}", part2);
+        let err = expect_parse_error(read_password_line("1-99999999999 a: abcde"));
+        assert_eq!(err.column, Some(3));
+    }
+
+    #[test]
+    fn out_of_range_positions() {
+        let line = read_password_line("0-9 a: abc").unwrap();
+        assert!(!Positions(Combine::Xor).is_valid(&line));
+    }
+
+    #[test]
+    fn audit_reasons() {
+        let content = "1-3 a: abcde\n1-3 b: cdefg\n1-3 a: aaaaa\nnot a line\n1-3 a: aba\n";
+        let entries = audit(content, &CountRange);
+        let outcomes: Vec<Outcome> = entries.iter().map(|entry| entry.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome::Valid,
+                Outcome::Invalid,
+                Outcome::Invalid,
+                Outcome::Malformed,
+                Outcome::Valid,
+            ]
+        );
+        assert_eq!(
+            entries[1].to_string(),
+            "line 2: invalid (letter 'b' appears 0 times, min 1)"
+        );
+        assert_eq!(
+            entries[2].reason.as_deref(),
+            Some("letter 'a' appears 5 times, max 3")
+        );
+        assert_eq!(entries[3].line, 4);
+
+        let entries = audit(content, &Positions(Combine::Xor));
+        assert_eq!(
+            entries[4].reason.as_deref(),
+            Some("both positions 1 and 3 are 'a'")
+        );
+
+        let csv = audit_to_csv(&[("count".to_owned(), audit(content, &CountRange))]);
+        let mut rows = csv.lines();
+        assert_eq!(rows.next(), Some("policy,line,outcome,reason"));
+        assert_eq!(rows.next(), Some("count,1,valid,"));
+        assert_eq!(
+            rows.next(),
+            Some("count,2,invalid,\"letter 'b' appears 0 times, min 1\"")
+        );
+    }
+
+    #[test]
+    fn audit_rejects_junk_around_lines() {
+        let content = "xx 1-3 b: cdefg\n1-3 b: cdefg!!\n1-3 a: ab-cd\n1-3 a: abcde\n";
+        let outcomes: Vec<Outcome> = audit(content, &CountRange)
+            .iter()
+            .map(|entry| entry.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome::Malformed,
+                Outcome::Malformed,
+                Outcome::Malformed,
+                Outcome::Valid,
+            ]
+        );
+    }
+
+    #[test]
+    fn policies_by_name() {
+        let rows = parse_password_file(EXAMPLE).unwrap();
+        let count = |spec: &str| count_valid(&rows, policy_by_name(spec).unwrap().as_ref());
+        assert_eq!(count("count"), 2);
+        assert_eq!(count("xor"), 1);
+        assert_eq!(count("and"), 1);
+        assert_eq!(count("or"), 2);
+        assert_eq!(count("forbidden=ab"), 2);
+        assert_eq!(count("regex=^c+$"), 1);
+        assert_eq!(policy_by_name("regex=^c+$").unwrap().name(), "regex=^c+$");
+
+        let err = expect_parse_error(policy_by_name("regex=(").map(|_| ()));
+        assert_eq!(err.column, Some(7));
+        expect_parse_error(policy_by_name("forbidden").map(|_| ()));
+        expect_parse_error(policy_by_name("length").map(|_| ()));
+    }
 }
@@ -1,76 +1,145 @@
 use crate::common::*;
+use crate::solution::Solution;
 
 #[derive(Debug, Clone)]
-struct TreeMap {
-    trees: Vec<Vec<bool>>,
+pub struct TreeMap {
+    trees: Grid<bool>,
 }
 
 impl TreeMap {
-    fn height(&self) -> usize {
-        self.trees.len()
+    pub fn height(&self) -> usize {
+        self.trees.height()
     }
 
-    fn is_tree(&self, x: usize, y: usize) -> bool {
-        let line = &self.trees[y];
-        let x = x % line.len();
-        line[x]
+    pub fn is_tree(&self, pos: Point) -> bool {
+        *self.trees.get_wrapping(pos.x as isize, pos.y as isize)
     }
 
-    fn count_slope(&self, dx: usize, dy: usize) -> usize {
-        let mut x = 0;
-        let mut y = 0;
+    pub fn count_slope(&self, slope: Vec2) -> usize {
+        let mut pos = Point::ORIGIN;
 
         let mut counter = 0;
-        while y < self.height() {
-            if self.is_tree(x, y) {
+        while pos.y < self.height() as i64 {
+            if self.is_tree(pos) {
                 counter += 1;
             }
 
-            x += dx;
-            y += dy;
+            pos += slope;
         }
         counter
     }
 
-    fn part1_count(&self) -> usize {
-        self.count_slope(3, 1)
+    pub(crate) fn part1_count(&self) -> usize {
+        self.count_slope(Vec2::new(3, 1))
     }
 
-    fn part2_count(&self) -> usize {
-        let diffs = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
-        let mut res = 1;
-        for &(dx, dy) in &diffs {
-            res *= self.count_slope(dx, dy);
-        }
-        res
+    pub(crate) fn part2_count(&self) -> AdventResult<usize> {
+        let slopes = [
+            Vec2::new(1, 1),
+            Vec2::new(3, 1),
+            Vec2::new(5, 1),
+            Vec2::new(7, 1),
+            Vec2::new(1, 2),
+        ];
+        slopes
+            .iter()
+            .try_fold(1usize, |product, &slope| {
+                product.checked_mul(self.count_slope(slope))
+            })
+            .ok_or_else(|| AdventError::Overflow("product of tree counts".to_owned()))
     }
 }
 
 impl std::fmt::Display for TreeMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for line in &self.trees {
-            for t in line {
-                write!(f, "{}", if *t { '#' } else { '.' })?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        let rendered = self.trees.render(|&t| if t { '#' } else { '.' });
+        write!(f, "{}", rendered)
+    }
+}
+
+pub fn parse_map(content: &str) -> AdventResult<TreeMap> {
+    let trees = Grid::parse(content, |c| match c {
+        '#' => Some(true),
+        '.' => Some(false),
+        _ => None,
+    })?;
+    Ok(TreeMap { trees })
+}
+
+/// A 31 columns wide map with `size` rows, a fifth of the squares being trees.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size.max(1) {
+        out.extend((0..31).map(|_| if rng.chance(20) { '#' } else { '.' }));
+        out.push('\n');
     }
+    out
 }
 
-fn read_map(path: &str) -> AdventResult<TreeMap> {
-    let content = std::fs::read_to_string(path)?;
+pub struct Day3;
+
+impl Solution for Day3 {
+    const DAY: u32 = 3;
+
+    type Input = TreeMap;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_map(content)
+    }
+
+    fn part1(tm: &Self::Input) -> AdventResult<usize> {
+        Ok(tm.part1_count())
+    }
+
+    fn part2(tm: &Self::Input) -> AdventResult<usize> {
+        tm.part2_count()
+    }
 
-    let mut lines = Vec::new();
-    for line in content.lines() {
-        let tree_line = line.chars().map(|c| c == '#').collect();
-        lines.push(tree_line);
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
     }
-    Ok(TreeMap { trees: lines })
 }
 
-pub fn run(path: &str) {
-    let tm = read_map(path).expect("Cannot read tree map");
-    println!("day3 part1: {}", tm.part1_count());
-    println!("day3 part2: {}", tm.part2_count());
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#
+";
+
+    #[test]
+    fn example() {
+        let tm = parse_map(EXAMPLE).unwrap();
+        assert_eq!(Day3::part1(&tm).unwrap(), 7);
+        assert_eq!(Day3::part2(&tm).unwrap(), 336);
+    }
+
+    #[test]
+    fn dense_forest() {
+        let tm = parse_map(&"#\n".repeat(12_000)).unwrap();
+        assert_eq!(Day3::part1(&tm).unwrap(), 12_000);
+        assert!(matches!(Day3::part2(&tm), Err(AdventError::Overflow(_))));
+    }
+
+    #[test]
+    fn malformed_map() {
+        let err = expect_parse_error(parse_map("..#\n.x.\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(2)));
+
+        let err = expect_parse_error(parse_map("..#\n\n"));
+        assert_eq!(err.line, Some(2));
+    }
 }
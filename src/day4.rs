@@ -1,10 +1,10 @@
 use crate::common::*;
-use core::panic;
+use crate::solution::Solution;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 #[derive(Debug, Default, Clone)]
-struct PassportInfo {
+pub struct PassportInfo {
     byr: Option<String>,
     iyr: Option<String>,
     eyr: Option<String>,
@@ -33,7 +33,7 @@ lazy_static! {
 }
 
 impl PassportInfo {
-    fn is_valid_part1(&self) -> bool {
+    pub(crate) fn is_valid_part1(&self) -> bool {
         self.byr.is_some()
             && self.iyr.is_some()
             && self.eyr.is_some()
@@ -43,7 +43,7 @@ impl PassportInfo {
             && self.pid.is_some()
     }
 
-    fn is_valid_part2(&self) -> bool {
+    pub(crate) fn is_valid_part2(&self) -> bool {
         fn year_check_builder(min: u32, max: u32) -> impl Fn(&str) -> bool {
             move |input: &str| match input.parse::<u32>() {
                 Ok(value) => min <= value && value <= max,
@@ -61,8 +61,8 @@ impl PassportInfo {
                 let unit = &captures[2];
 
                 match (value.parse::<u32>(), unit) {
-                    (Ok(value), "cm") => 150 <= value && value <= 193,
-                    (Ok(value), "in") => 59 <= value && value <= 76,
+                    (Ok(value), "cm") => (150..=193).contains(&value),
+                    (Ok(value), "in") => (59..=76).contains(&value),
                     _ => false,
                 }
             } else {
@@ -82,55 +82,210 @@ impl PassportInfo {
     }
 }
 
-fn read_pairs<'a>(lines: &[&'a str]) -> Vec<(&'a str, &'a str)> {
+fn read_pairs(line: &str) -> AdventResult<Vec<(&str, &str)>> {
     let mut pairs = Vec::new();
-    for line in lines {
-        for part in line.split_ascii_whitespace() {
-            let raw_pair: Vec<_> = part.split(':').collect();
-            assert_eq!(raw_pair.len(), 2);
-            pairs.push((raw_pair[0], raw_pair[1]))
+    for part in line.split_ascii_whitespace() {
+        let raw_pair: Vec<_> = part.split(':').collect();
+        if raw_pair.len() != 2 {
+            return Err(AdventError::parse_at(
+                "Expected a key:value pair",
+                line,
+                part,
+            ));
         }
+        pairs.push((raw_pair[0], raw_pair[1]))
     }
-    pairs
+    Ok(pairs)
 }
 
-fn read_passport(lines: &[&str]) -> PassportInfo {
+/// Parses one passport block; `lines` must be subslices of `content` so
+/// errors can point at the right line.
+pub(crate) fn read_passport(content: &str, lines: &[&str]) -> AdventResult<PassportInfo> {
     let mut info = PassportInfo::default();
 
-    for (key, value) in read_pairs(lines) {
-        let field = match key {
-            "byr" => &mut info.byr,
-            "iyr" => &mut info.iyr,
-            "eyr" => &mut info.eyr,
-            "hgt" => &mut info.hgt,
-            "hcl" => &mut info.hcl,
-            "ecl" => &mut info.ecl,
-            "pid" => &mut info.pid,
-            "cid" => &mut info.cid,
-            _ => panic!("Unknown field"),
-        };
-        *field = Some(value.into());
+    for line in lines {
+        let line_number = line_number_of(content, line);
+        for (key, value) in read_pairs(line).map_err(|err| err.at_line(line_number))? {
+            let field = match key {
+                "byr" => &mut info.byr,
+                "iyr" => &mut info.iyr,
+                "eyr" => &mut info.eyr,
+                "hgt" => &mut info.hgt,
+                "hcl" => &mut info.hcl,
+                "ecl" => &mut info.ecl,
+                "pid" => &mut info.pid,
+                "cid" => &mut info.cid,
+                _ => {
+                    let err = AdventError::parse_at("Unknown field", line, key);
+                    return Err(err.at_line(line_number));
+                }
+            };
+            *field = Some(value.into());
+        }
     }
 
-    info
+    Ok(info)
+}
+
+pub fn parse_passports(content: &str) -> AdventResult<Vec<PassportInfo>> {
+    split_blocks(content)
+        .map(|block| read_passport(content, &block))
+        .collect()
 }
 
-fn read_passports(path: &str) -> AdventResult<Vec<PassportInfo>> {
-    let content = std::fs::read_to_string(path)?;
+const FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+const EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+
+fn generate_field(rng: &mut Rng, key: &str, valid: bool) -> String {
+    match (key, valid) {
+        ("byr", true) => rng.range(1920, 2003).to_string(),
+        ("byr", false) => rng.range(1850, 1920).to_string(),
+        ("iyr", true) => rng.range(2010, 2021).to_string(),
+        ("iyr", false) => rng.range(2021, 2031).to_string(),
+        ("eyr", true) => rng.range(2020, 2031).to_string(),
+        ("eyr", false) => rng.range(1990, 2020).to_string(),
+        ("hgt", true) if rng.chance(50) => format!("{}cm", rng.range(150, 194)),
+        ("hgt", true) => format!("{}in", rng.range(59, 77)),
+        ("hgt", false) if rng.chance(50) => format!("{}in", rng.range(77, 194)),
+        ("hgt", false) => rng.range(59, 194).to_string(),
+        ("hcl", true) => format!("#{:06x}", rng.range(0, 1 << 24)),
+        ("hcl", false) => format!("{:06x}", rng.range(0, 1 << 24)),
+        ("ecl", true) => rng.choose(&EYE_COLORS).to_string(),
+        ("ecl", false) => rng.choose(&["wat", "xry", "zzz"]).to_string(),
+        ("pid", true) => format!("{:09}", rng.range(0, 1_000_000_000)),
+        ("pid", false) => format!("{:010}", rng.range(0, 10_000_000_000)),
+        _ => rng.range(100, 350).to_string(),
+    }
+}
+
+/// `size` passports, each field missing or breaking its part 2 rule now and then.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut passports = Vec::with_capacity(size);
+    for _ in 0..size {
+        let mut fields = Vec::new();
+        for &key in &FIELDS {
+            if rng.chance(if key == "cid" { 50 } else { 92 }) {
+                let valid = rng.chance(90);
+                fields.push(format!("{}:{}", key, generate_field(rng, key, valid)));
+            }
+        }
+        if fields.is_empty() {
+            fields.push(format!("cid:{}", generate_field(rng, "cid", true)));
+        }
+        rng.shuffle(&mut fields);
+
+        let mut passport = String::new();
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                passport.push(if rng.chance(30) { '\n' } else { ' ' });
+            }
+            passport.push_str(field);
+        }
+        passport.push('\n');
+        passports.push(passport);
+    }
+    passports.join("\n")
+}
+
+pub struct Day4;
+
+impl Solution for Day4 {
+    const DAY: u32 = 4;
+
+    type Input = Vec<PassportInfo>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_passports(content)
+    }
+
+    fn part1(passports: &Self::Input) -> AdventResult<usize> {
+        Ok(passports.iter().filter(|pi| pi.is_valid_part1()).count())
+    }
+
+    fn part2(passports: &Self::Input) -> AdventResult<usize> {
+        Ok(passports.iter().filter(|pi| pi.is_valid_part2()).count())
+    }
 
-    let passports = split_blocks(&content)
-        .iter()
-        .map(|block| read_passport(block))
-        .collect();
-    Ok(passports)
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let passports = read_passports(path).expect("Cannot read passports");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017 cid:147 hgt:183cm
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929
+
+hcl:#ae17e1 iyr:2013
+eyr:2024
+ecl:brn pid:760753108 byr:1931
+hgt:179cm
+
+hcl:#cfa07d eyr:2025 pid:166559648
+iyr:2011 ecl:brn hgt:59in
+";
+
+    const INVALID_PART2: &str = "\
+eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+iyr:2019
+hcl:#602927 eyr:1967 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+hcl:dab227 iyr:2012
+ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277
 
-    let part1_count = passports.iter().filter(|pi| pi.is_valid_part1()).count();
-    println!("day4 part1: {}", part1_count);
+hgt:59cm ecl:zzz
+eyr:2038 hcl:74454a iyr:2023
+pid:3556412378 byr:2007
+";
 
-    let part2_count = passports.iter().filter(|pi| pi.is_valid_part2()).count();
-    println!("day4 part2: {}", part2_count);
+    const VALID_PART2: &str = "\
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f
+
+eyr:2029 ecl:blu cid:129 byr:1989
+iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm
+
+hcl:#888785
+hgt:164cm byr:2001 iyr:2015 cid:88
+pid:545766238 ecl:hzl
+eyr:2022
+
+iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719
+";
+
+    #[test]
+    fn example() {
+        let passports = parse_passports(EXAMPLE).unwrap();
+        assert_eq!(Day4::part1(&passports).unwrap(), 2);
+    }
+
+    #[test]
+    fn example_part2() {
+        let invalid = parse_passports(INVALID_PART2).unwrap();
+        assert_eq!(Day4::part2(&invalid).unwrap(), 0);
+
+        let valid = parse_passports(VALID_PART2).unwrap();
+        assert_eq!(Day4::part2(&valid).unwrap(), 4);
+    }
+
+    #[test]
+    fn malformed_fields() {
+        let err = expect_parse_error(parse_passports("byr:1937\n\niyr:2017 foo:bar\n"));
+        assert_eq!((err.line, err.column), (Some(3), Some(10)));
+        assert_eq!(err.text, "foo");
+
+        let err = expect_parse_error(parse_passports("byr:1937 iyr2017\n"));
+        assert_eq!((err.line, err.column), (Some(1), Some(10)));
+    }
 }
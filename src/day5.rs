@@ -1,50 +1,46 @@
 use crate::common::*;
+use crate::solution::Solution;
 use std::collections::HashSet;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-struct Pass {
-    row: u32,
-    column: u32,
+pub struct Pass {
+    pub row: u32,
+    pub column: u32,
 }
 
 impl Pass {
-    fn seat_id(self) -> u32 {
+    pub fn seat_id(self) -> u32 {
         self.row * 8 + self.column
     }
 }
 
-fn read_pass(line: &str) -> Pass {
-    let line = line.trim();
-
-    let mut row_coeff = 64;
-    let mut row = 0;
-    for c in line.chars().take(7) {
-        match c {
-            'B' => row += row_coeff,
-            'F' => {}
-            _ => unreachable!(),
-        }
-        row_coeff >>= 1;
+pub(crate) fn read_pass(line: &str) -> AdventResult<Pass> {
+    let code = line.trim();
+    if code.len() != 10 {
+        return Err(AdventError::parse_at("Expected 10 characters", line, code));
     }
 
-    let mut col_coeff = 4;
+    let mut row = 0;
     let mut column = 0;
-    for c in line.chars().skip(7).take(3) {
-        match c {
-            'R' => column += col_coeff,
-            'L' => {}
-            _ => unreachable!(),
-        }
-        col_coeff >>= 1;
+    for (offset, c) in code.char_indices() {
+        let (value, bit) = match (offset, c) {
+            (0..=6, 'B') => (&mut row, 1),
+            (0..=6, 'F') => (&mut row, 0),
+            (7..=9, 'R') => (&mut column, 1),
+            (7..=9, 'L') => (&mut column, 0),
+            _ => {
+                let fragment = &code[offset..offset + c.len_utf8()];
+                return Err(AdventError::parse_at("Unknown seat char", line, fragment));
+            }
+        };
+        *value = (*value << 1) | bit;
     }
 
-    Pass { row, column }
+    Ok(Pass { row, column })
 }
 
-fn read_passes(path: &str) -> AdventResult<Vec<Pass>> {
-    let content = std::fs::read_to_string(path)?;
-    let passes = content.lines().map(read_pass).collect();
-    Ok(passes)
+pub fn parse_passes(content: &str) -> AdventResult<Vec<Pass>> {
+    parse_lines_with(content, read_pass)
 }
 
 fn find_missing_passes(pass_set: &HashSet<Pass>) -> Vec<Pass> {
@@ -61,7 +57,7 @@ fn find_missing_passes(pass_set: &HashSet<Pass>) -> Vec<Pass> {
 }
 
 fn find_possible_passes(passes: &[Pass]) -> Vec<Pass> {
-    let pass_set: HashSet<Pass> = passes.into_iter().copied().collect();
+    let pass_set: HashSet<Pass> = passes.iter().copied().collect();
     let possibles = find_missing_passes(&pass_set)
         .into_iter()
         .filter(|pass| pass.row != 0 && pass.row != 127);
@@ -75,22 +71,114 @@ fn find_possible_passes(passes: &[Pass]) -> Vec<Pass> {
         .collect()
 }
 
-fn find_part2_seat_id(passes: &[Pass]) -> u32 {
+pub(crate) fn find_part2_seat_id(passes: &[Pass]) -> AdventResult<u32> {
     let possibles = find_possible_passes(passes);
-    assert_eq!(possibles.len(), 1);
+    match possibles.as_slice() {
+        [pass] => Ok(pass.seat_id()),
+        [] => Err(AdventError::NoSolution),
+        _ => Err(AdventError::Solver(format!(
+            "{} candidate seats found",
+            possibles.len()
+        ))),
+    }
+}
 
-    possibles[0].seat_id()
+fn encode_pass(seat_id: u32) -> String {
+    let (row, column) = (seat_id / 8, seat_id % 8);
+    let row_part = (0..7)
+        .rev()
+        .map(|bit| if row >> bit & 1 == 1 { 'B' } else { 'F' });
+    let column_part = (0..3)
+        .rev()
+        .map(|bit| if column >> bit & 1 == 1 { 'R' } else { 'L' });
+    row_part.chain(column_part).collect()
 }
 
-pub fn run(path: &str) {
-    let passes = read_passes(path).expect("Cannot read passes");
+/// A contiguous block of about `size` seats (at most 1000) with a single
+/// seat missing inside it.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let size = size.clamp(3, 1000) as u64;
+    let first = rng.range(1, 1023 - size);
+    let missing = rng.range(first + 1, first + size - 1);
 
-    let part1_max = passes
+    let mut seat_ids: Vec<u32> = (first..first + size)
+        .filter(|&seat_id| seat_id != missing)
+        .map(|seat_id| seat_id as u32)
+        .collect();
+    rng.shuffle(&mut seat_ids);
+
+    seat_ids
         .iter()
-        .map(|pass| pass.seat_id())
-        .max()
-        .expect("Cannot take the max of the seat ids");
+        .map(|&seat_id| encode_pass(seat_id) + "\n")
+        .collect()
+}
+
+pub struct Day5;
+
+impl Solution for Day5 {
+    const DAY: u32 = 5;
+
+    type Input = Vec<Pass>;
+    type Answer1 = u32;
+    type Answer2 = u32;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_passes(content)
+    }
 
-    println!("day5 part1: {}", part1_max);
-    println!("day5 part2: {:?}", find_part2_seat_id(&passes));
+    fn part1(passes: &Self::Input) -> AdventResult<u32> {
+        passes
+            .iter()
+            .map(|pass| pass.seat_id())
+            .max()
+            .ok_or(AdventError::NoSolution)
+    }
+
+    fn part2(passes: &Self::Input) -> AdventResult<u32> {
+        find_part2_seat_id(passes)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+BFFFBBFRRR
+FFFBBBFRRR
+BBFFBBFRLL
+";
+
+    #[test]
+    fn example() {
+        let passes = parse_passes(EXAMPLE).unwrap();
+        let ids: Vec<_> = passes.iter().map(|pass| pass.seat_id()).collect();
+        assert_eq!(ids, vec![567, 119, 820]);
+        assert_eq!(Day5::part1(&passes).unwrap(), 820);
+    }
+
+    #[test]
+    fn missing_seat() {
+        let passes: Vec<_> = (8..32)
+            .filter(|&id| id != 17)
+            .map(|id| Pass {
+                row: id / 8,
+                column: id % 8,
+            })
+            .collect();
+        assert_eq!(Day5::part2(&passes).unwrap(), 17);
+    }
+
+    #[test]
+    fn malformed_pass() {
+        let err = expect_parse_error(parse_passes("BFFFBBFRRR\nBFFFXBFRRR\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(5)));
+
+        let err = expect_parse_error(read_pass("BFFFBBFRR"));
+        assert_eq!(err.text, "BFFFBBFRR");
+    }
 }
@@ -1,33 +1,31 @@
 use crate::common::*;
+use crate::solution::Solution;
 use itertools::Itertools;
 use std::collections::HashSet;
 
-type AnswerGroup = Vec<HashSet<char>>;
+pub type AnswerGroup = Vec<HashSet<char>>;
 
-fn read_answer_groups(path: &str) -> AdventResult<Vec<AnswerGroup>> {
-    let content = std::fs::read_to_string(path)?;
-    let mut groups = Vec::new();
-    for block in split_blocks(&content) {
-        let mut group = Vec::new();
-        for answer in block {
-            group.push(answer.chars().collect());
-        }
-        groups.push(group);
-    }
-    Ok(groups)
+pub fn parse_answer_groups(content: &str) -> AdventResult<Vec<AnswerGroup>> {
+    Ok(split_blocks(content)
+        .map(|block| {
+            block
+                .iter()
+                .map(|answer| answer.chars().collect())
+                .collect()
+        })
+        .collect())
 }
 
 fn inner_part_count(
-    path: &str,
+    groups: &[AnswerGroup],
     merger: fn(&HashSet<char>, &HashSet<char>) -> HashSet<char>,
 ) -> usize {
-    let groups = read_answer_groups(path).expect("Cannot read answer groups");
-
     groups
-        .into_iter()
+        .iter()
         .map(|group| {
             group
-                .into_iter()
+                .iter()
+                .cloned()
                 .fold1(|a, b| merger(&a, &b))
                 .unwrap_or_default()
         })
@@ -35,18 +33,95 @@ fn inner_part_count(
         .sum()
 }
 
-fn part1_count(path: &str) -> usize {
-    inner_part_count(path, |a, b| a | b)
+pub(crate) fn part1_count(groups: &[AnswerGroup]) -> usize {
+    inner_part_count(groups, |a, b| a | b)
+}
+
+pub(crate) fn part2_count(groups: &[AnswerGroup]) -> usize {
+    inner_part_count(groups, |a, b| a & b)
 }
 
-fn part2_count(path: &str) -> usize {
-    inner_part_count(path, |a, b| a & b)
+/// `size` groups of one to five people answering a few questions each.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut groups = Vec::with_capacity(size);
+    for _ in 0..size {
+        let mut group = String::new();
+        for _ in 0..rng.range(1, 6) {
+            let mut answers: String = (b'a'..=b'z')
+                .filter(|_| rng.chance(30))
+                .map(char::from)
+                .collect();
+            if answers.is_empty() {
+                answers.push((b'a' + rng.range(0, 26) as u8) as char);
+            }
+            group.push_str(&answers);
+            group.push('\n');
+        }
+        groups.push(group);
+    }
+    groups.join("\n")
+}
+
+pub struct Day6;
+
+impl Solution for Day6 {
+    const DAY: u32 = 6;
+
+    type Input = Vec<AnswerGroup>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_answer_groups(content)
+    }
+
+    fn part1(groups: &Self::Input) -> AdventResult<usize> {
+        Ok(part1_count(groups))
+    }
+
+    fn part2(groups: &Self::Input) -> AdventResult<usize> {
+        Ok(part2_count(groups))
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let part1_count: usize = part1_count(path);
-    println!("day6 part1: {}", part1_count);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+abc
 
-    let part2_count: usize = part2_count(path);
-    println!("day6 part1: {}", part2_count);
+a
+b
+c
+
+ab
+ac
+
+a
+a
+a
+a
+
+b
+";
+
+    #[test]
+    fn example() {
+        let groups = parse_answer_groups(EXAMPLE).unwrap();
+        assert_eq!(Day6::part1(&groups).unwrap(), 11);
+        assert_eq!(Day6::part2(&groups).unwrap(), 6);
+    }
+
+    #[test]
+    fn windows_line_endings() {
+        let content = EXAMPLE.replace('\n', "\r\n");
+        let groups = parse_answer_groups(&content).unwrap();
+        assert_eq!(Day6::part1(&groups).unwrap(), 11);
+        assert_eq!(Day6::part2(&groups).unwrap(), 6);
+    }
 }
@@ -1,55 +1,53 @@
 use crate::common::*;
+use crate::solution::Solution;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 
 lazy_static! {
-    static ref BAG_RE: Regex = Regex::new(r"(\d+) (.+) bags?").unwrap();
+    static ref BAG_RE: Regex = Regex::new(r"^(?P<count>\d+) (?P<color>.+) bags?$").unwrap();
 }
 
 #[derive(Debug, Clone)]
-struct Rule {
-    color: String,
-    containing: HashMap<String, usize>,
+pub struct Rule {
+    pub color: String,
+    pub containing: HashMap<String, usize>,
 }
 
-fn parse_sub_rule(sub_rule: &str) -> (String, usize) {
-    if let Some(captures) = BAG_RE.captures(sub_rule) {
-        let count = captures[1].parse().expect("Cannot parse sub rule count");
-        let color = captures[2].to_owned();
-        (color, count)
-    } else {
-        panic!("Cannot parse sub rule:  {}", sub_rule)
-    }
+fn parse_sub_rule(line: &str, sub_rule: &str) -> AdventResult<(String, usize)> {
+    let captures = BAG_RE
+        .captures(sub_rule)
+        .ok_or_else(|| AdventError::parse_at("Cannot parse sub rule", line, sub_rule))?;
+    let count = parse_token(line, &captures["count"])?;
+    Ok((captures["color"].to_owned(), count))
 }
 
-fn parse_rule(line: &str) -> Rule {
-    let line = line.trim_end_matches('.');
+pub(crate) fn parse_rule(line: &str) -> AdventResult<Rule> {
+    let trimmed = line.trim_end_matches('.');
 
-    let main_parts: Vec<_> = line.split(" contain ").collect();
-    assert_eq!(main_parts.len(), 2);
-    let color = main_parts[0].trim_end_matches(" bags").to_owned();
+    let (color, containing_part) = split_pair(line, trimmed, " contain ")?;
+    let color = color.trim_end_matches(" bags").to_owned();
 
-    let containing_part = main_parts[1];
     let containing = if containing_part == "no other bags" {
         HashMap::new()
     } else {
-        containing_part.split(", ").map(parse_sub_rule).collect()
+        containing_part
+            .split(", ")
+            .map(|sub_rule| parse_sub_rule(line, sub_rule))
+            .collect::<AdventResult<_>>()?
     };
 
-    Rule { color, containing }
+    Ok(Rule { color, containing })
 }
 
-fn parse_rules(path: &str) -> AdventResult<Vec<Rule>> {
-    let content = std::fs::read_to_string(path)?;
-    let rules = content.lines().map(parse_rule).collect();
-    Ok(rules)
+pub fn parse_rules(content: &str) -> AdventResult<Vec<Rule>> {
+    parse_lines_with(content, parse_rule)
 }
 
-fn build_parent_tree(rules: &[Rule]) -> HashMap<String, Vec<String>> {
+pub(crate) fn build_parent_tree(rules: &[Rule]) -> HashMap<String, Vec<String>> {
     let mut tree: HashMap<String, Vec<String>> = HashMap::new();
     for rule in rules {
-        for (child, _) in &rule.containing {
+        for child in rule.containing.keys() {
             tree.entry(child.clone())
                 .or_default()
                 .push(rule.color.clone());
@@ -58,7 +56,7 @@ fn build_parent_tree(rules: &[Rule]) -> HashMap<String, Vec<String>> {
     tree
 }
 
-fn part1_count(tree: &HashMap<String, Vec<String>>) -> usize {
+pub(crate) fn part1_count(tree: &HashMap<String, Vec<String>>) -> usize {
     let mut open_queue = vec!["shiny gold".to_owned()];
     let mut visited = HashSet::new();
 
@@ -75,31 +73,269 @@ fn part1_count(tree: &HashMap<String, Vec<String>>) -> usize {
     visited.len() - 1 // -1 for the shiny gold that was visited
 }
 
-fn count_bags(bag_color: &str, tree: &HashMap<String, HashMap<String, usize>>) -> usize {
+fn cycle_error(color: &str) -> AdventError {
+    AdventError::Solver(format!("{} bags end up inside themselves", color))
+}
+
+/// Walks down the contents of every color separately; slow, but a simple
+/// reference for `part1_count`. Fails on rules where a bag holds itself.
+#[cfg(test)]
+pub(crate) fn part1_count_raw(rules: &[Rule]) -> AdventResult<usize> {
+    fn holds_gold<'r>(
+        color: &'r str,
+        rules: &HashMap<&str, &'r Rule>,
+        path: &mut Vec<&'r str>,
+    ) -> AdventResult<bool> {
+        let rule = match rules.get(color) {
+            Some(&rule) => rule,
+            None => return Ok(false),
+        };
+        if path.contains(&color) {
+            return Err(cycle_error(color));
+        }
+
+        path.push(color);
+        let mut found = false;
+        for child in rule.containing.keys() {
+            if child == "shiny gold" || holds_gold(child, rules, path)? {
+                found = true;
+                break;
+            }
+        }
+        path.pop();
+        Ok(found)
+    }
+
+    let by_color: HashMap<&str, &Rule> = rules
+        .iter()
+        .map(|rule| (rule.color.as_str(), rule))
+        .collect();
+    let mut count = 0;
+    for rule in rules {
+        if holds_gold(&rule.color, &by_color, &mut Vec::new())? {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn count_bags<'t>(
+    bag_color: &'t str,
+    tree: &'t HashMap<String, HashMap<String, usize>>,
+    path: &mut Vec<&'t str>,
+) -> AdventResult<usize> {
     if let Some(children) = tree.get(bag_color) {
+        if path.contains(&bag_color) {
+            return Err(cycle_error(bag_color));
+        }
+
+        path.push(bag_color);
         let mut res = 1;
         for (child, count) in children {
-            res += count_bags(child, tree) * count;
+            res = count_bags(child, tree, path)?
+                .checked_mul(*count)
+                .and_then(|inside| inside.checked_add(res))
+                .ok_or_else(|| AdventError::Overflow("bag count".to_owned()))?;
         }
-        res
+        path.pop();
+        Ok(res)
     } else {
-        1
+        Ok(1)
     }
 }
 
-fn part2_count(rules: &[Rule]) -> usize {
+pub(crate) fn part2_count(rules: &[Rule]) -> AdventResult<usize> {
     let mut children: HashMap<String, HashMap<String, usize>> = HashMap::new();
     for rule in rules {
         children.insert(rule.color.clone(), rule.containing.clone());
     }
-    count_bags("shiny gold", &children) - 1 // -1 for the main bag
+    Ok(count_bags("shiny gold", &children, &mut Vec::new())? - 1) // -1 for the main bag
+}
+
+const ADJECTIVES: [&str; 16] = [
+    "bright", "clear", "dark", "dim", "dotted", "drab", "dull", "faded", "light", "mirrored",
+    "muted", "pale", "plaid", "posh", "striped", "vibrant",
+];
+const HUES: [&str; 16] = [
+    "aqua", "beige", "black", "blue", "coral", "crimson", "cyan", "gold", "gray", "green", "lime",
+    "olive", "orange", "plum", "red", "white",
+];
+const LAYERS: usize = 6;
+
+fn bag_color(index: usize) -> String {
+    let (adjectives, hues) = (ADJECTIVES.len(), HUES.len());
+    let color = format!(
+        "{} {}",
+        ADJECTIVES[index % adjectives],
+        HUES[index / adjectives % hues]
+    );
+    match index / (adjectives * hues) {
+        0 => color,
+        extra => format!("{} {}", ADJECTIVES[(extra - 1) % adjectives], color),
+    }
+}
+
+/// Rules for about `size` colors forming a DAG of a few layers, so that the
+/// number of bags inside any bag stays small. "shiny gold" sits in the middle
+/// layer and is always held by at least one bag.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let max_colors = ADJECTIVES.len() * HUES.len() * (ADJECTIVES.len() + 1);
+    let size = size.max(LAYERS).min(max_colors);
+    let layer_start = |layer: usize| (layer * size).div_ceil(LAYERS);
+    let layer_of = |index: usize| index * LAYERS / size;
+
+    let gold = size / 2;
+    let colors: Vec<String> = (0..size)
+        .map(|index| {
+            if index == gold {
+                "shiny gold".to_owned()
+            } else {
+                bag_color(index)
+            }
+        })
+        .collect();
+
+    let mut rules = Vec::with_capacity(size);
+    for index in 0..size {
+        let next_layer = layer_start(layer_of(index) + 1);
+        let mut children = Vec::new();
+        if next_layer < size {
+            for _ in 0..rng.range(0, 5) {
+                children.push(rng.range(next_layer as u64, size as u64) as usize);
+            }
+        }
+        if index == 0 {
+            children.push(gold);
+        }
+        children.sort_unstable();
+        children.dedup();
+
+        let contents: Vec<String> = children
+            .iter()
+            .map(|&child| match rng.range(1, 6) {
+                1 => format!("1 {} bag", colors[child]),
+                count => format!("{} {} bags", count, colors[child]),
+            })
+            .collect();
+        let contents = if contents.is_empty() {
+            "no other bags".to_owned()
+        } else {
+            contents.join(", ")
+        };
+        rules.push(format!("{} bags contain {}.\n", colors[index], contents));
+    }
+
+    rng.shuffle(&mut rules);
+    rules.concat()
+}
+
+pub struct Day7;
+
+impl Solution for Day7 {
+    const DAY: u32 = 7;
+
+    type Input = Vec<Rule>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_rules(content)
+    }
+
+    fn part1(rules: &Self::Input) -> AdventResult<usize> {
+        let tree = build_parent_tree(rules);
+        Ok(part1_count(&tree))
+    }
+
+    fn part2(rules: &Self::Input) -> AdventResult<usize> {
+        part2_count(rules)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
 }
 
-pub fn run(path: &str) {
-    let rules = parse_rules(path).expect("Cannot parse rules");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.
+";
+
+    const EXAMPLE_PART2: &str = "\
+shiny gold bags contain 2 dark red bags.
+dark red bags contain 2 dark orange bags.
+dark orange bags contain 2 dark yellow bags.
+dark yellow bags contain 2 dark green bags.
+dark green bags contain 2 dark blue bags.
+dark blue bags contain 2 dark violet bags.
+dark violet bags contain no other bags.
+";
+
+    #[test]
+    fn example() {
+        let rules = parse_rules(EXAMPLE).unwrap();
+        assert_eq!(Day7::part1(&rules).unwrap(), 4);
+        assert_eq!(Day7::part2(&rules).unwrap(), 32);
+
+        let rules = parse_rules(EXAMPLE_PART2).unwrap();
+        assert_eq!(Day7::part2(&rules).unwrap(), 126);
+    }
+
+    #[test]
+    fn bags_inside_themselves() {
+        let rules = parse_rules("shiny gold bags contain 1 shiny gold bag.\n").unwrap();
+        assert_eq!(Day7::part1(&rules).unwrap(), 0);
+        assert!(matches!(Day7::part2(&rules), Err(AdventError::Solver(_))));
 
-    let tree = build_parent_tree(&rules);
-    println!("day7 part1: {}", part1_count(&tree));
+        let rules = parse_rules(
+            "\
+bright white bags contain 1 shiny gold bag, 2 muted yellow bags.
+muted yellow bags contain 1 bright white bag.
+shiny gold bags contain 3 faded blue bags.
+faded blue bags contain 1 dotted black bag.
+dotted black bags contain 2 faded blue bags.
+",
+        )
+        .unwrap();
+        assert_eq!(Day7::part1(&rules).unwrap(), 2);
+        match Day7::part2(&rules) {
+            Err(AdventError::Solver(message)) => assert!(message.ends_with("inside themselves")),
+            other => panic!("expected a solver error, got {:?}", other),
+        }
+        let err = part1_count_raw(&rules).unwrap_err();
+        assert!(err.to_string().contains("inside themselves"));
+    }
 
-    println!("day7 part2: {}", part2_count(&rules));
+    #[test]
+    fn huge_bag_count() {
+        let rules = parse_rules(
+            "\
+shiny gold bags contain 99999999999 dark red bags.
+dark red bags contain 99999999999 dark blue bags.
+dark blue bags contain no other bags.
+",
+        )
+        .unwrap();
+        assert!(matches!(Day7::part2(&rules), Err(AdventError::Overflow(_))));
+    }
+
+    #[test]
+    fn malformed_rule() {
+        let err = expect_parse_error(parse_rules("faded blue bags hold nothing.\n"));
+        assert_eq!(err.line, Some(1));
+
+        let err = expect_parse_error(parse_rule("light red bags contain one bright white bag."));
+        assert_eq!(err.column, Some(24));
+    }
 }
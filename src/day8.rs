@@ -1,43 +1,46 @@
 use std::collections::HashSet;
 
-use crate::common::AdventResult;
+use crate::common::*;
+use crate::solution::Solution;
 
 #[derive(Debug, Clone, Copy)]
-enum InstructionKind {
+pub enum InstructionKind {
     Nop,
     Acc,
     Jmp,
 }
 
 #[derive(Debug, Clone, Copy)]
-struct Instruction {
-    kind: InstructionKind,
-    value: i32,
+pub struct Instruction {
+    pub kind: InstructionKind,
+    pub value: i32,
 }
 
-fn parse_instruction(line: &str) -> AdventResult<Instruction> {
-    let line = line.trim();
-
+pub(crate) fn parse_instruction(line: &str) -> AdventResult<Instruction> {
     let parts: Vec<_> = line.split_ascii_whitespace().collect();
-    assert_eq!(parts.len(), 2);
+    if parts.len() != 2 {
+        return Err(AdventError::parse(
+            "Expected '<operation> <argument>'",
+            line,
+        ));
+    }
 
-    let value: i32 = parts[1].parse()?;
+    let value: i32 = parse_token(line, parts[1])?;
 
     let kind = match parts[0] {
         "nop" => InstructionKind::Nop,
         "acc" => InstructionKind::Acc,
         "jmp" => InstructionKind::Jmp,
-        _ => panic!("Unknown instruction"),
+        _ => return Err(AdventError::parse_at("Unknown instruction", line, parts[0])),
     };
     Ok(Instruction { kind, value })
 }
 
-fn read_instructions(path: &str) -> AdventResult<Vec<Instruction>> {
-    let content = std::fs::read_to_string(path)?;
-    content.lines().map(parse_instruction).collect()
+pub fn parse_instructions(content: &str) -> AdventResult<Vec<Instruction>> {
+    parse_lines_with(content, parse_instruction)
 }
 
-struct VM<'i> {
+pub struct VM<'i> {
     instructions: &'i [Instruction],
     pc: usize,
     acc: i32,
@@ -45,7 +48,7 @@ struct VM<'i> {
 }
 
 impl<'i> VM<'i> {
-    fn new(instructions: &'i [Instruction]) -> Self {
+    pub fn new(instructions: &'i [Instruction]) -> Self {
         VM {
             instructions,
             pc: 0,
@@ -54,11 +57,19 @@ impl<'i> VM<'i> {
         }
     }
 
-    fn is_finished(&self) -> bool {
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn acc(&self) -> i32 {
+        self.acc
+    }
+
+    pub fn is_finished(&self) -> bool {
         self.pc >= self.instructions.len()
     }
 
-    fn part1_check(&self) -> Option<i32> {
+    pub(crate) fn part1_check(&self) -> Option<i32> {
         if self.visited_pcs.contains(&self.pc) {
             Some(self.acc)
         } else {
@@ -66,42 +77,69 @@ impl<'i> VM<'i> {
         }
     }
 
-    fn step(&mut self) {
+    /// Runs the instruction at `pc`. Fails on a finished program, a jump
+    /// before the first instruction or an accumulator overflow.
+    pub(crate) fn step(&mut self) -> AdventResult<()> {
+        let inst = *self
+            .instructions
+            .get(self.pc)
+            .ok_or_else(|| AdventError::Solver("the program has already finished".to_owned()))?;
         self.visited_pcs.insert(self.pc);
 
-        let inst = self.instructions[self.pc];
         match inst.kind {
             InstructionKind::Nop => {
                 self.pc += 1;
             }
             InstructionKind::Acc => {
-                self.acc += inst.value;
+                self.acc = self
+                    .acc
+                    .checked_add(inst.value)
+                    .ok_or_else(|| AdventError::Overflow("accumulator".to_owned()))?;
                 self.pc += 1;
             }
             InstructionKind::Jmp => {
                 let offset = inst.value;
-                if offset < 0 {
-                    self.pc -= (-offset) as usize;
+                let target = if offset < 0 {
+                    self.pc.checked_sub(offset.unsigned_abs() as usize)
                 } else {
-                    self.pc += offset as usize;
-                }
+                    self.pc.checked_add(offset as usize)
+                };
+                self.pc = target.ok_or_else(|| {
+                    AdventError::Solver(format!(
+                        "jmp {:+} at line {} leaves the program",
+                        offset,
+                        self.pc + 1
+                    ))
+                })?;
             }
         }
+        Ok(())
     }
-}
 
-fn vm_run(instructions: &[Instruction]) -> Result<i32, i32> {
-    let mut vm = VM::new(instructions);
-    while !vm.is_finished() {
-        if let Some(acc) = vm.part1_check() {
-            return Err(acc);
+    /// Runs until the program halts or is about to repeat an instruction.
+    pub fn run(&mut self) -> AdventResult<Exit> {
+        while !self.is_finished() {
+            if let Some(acc) = self.part1_check() {
+                return Ok(Exit::Looped(acc));
+            }
+            self.step()?;
         }
-        vm.step();
+        Ok(Exit::Halted(self.acc))
     }
-    Ok(vm.acc)
 }
 
-fn part2_search(instructions: &[Instruction]) -> Option<i32> {
+/// How a program run ends, with the accumulator at that point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Halted(i32),
+    Looped(i32),
+}
+
+pub(crate) fn vm_run(instructions: &[Instruction]) -> AdventResult<Exit> {
+    VM::new(instructions).run()
+}
+
+pub(crate) fn part2_search(instructions: &[Instruction]) -> Option<i32> {
     for (index, inst) in instructions.iter().enumerate() {
         let replacement_kind = match inst.kind {
             InstructionKind::Nop => Some(InstructionKind::Jmp),
@@ -117,8 +155,8 @@ fn part2_search(instructions: &[Instruction]) -> Option<i32> {
         if let Some(replacement) = replacement {
             let mut copy = instructions.to_vec();
             copy[index] = replacement;
-            let new_res = vm_run(&copy);
-            if let Ok(acc) = new_res {
+            // A patch that jumps out of the program does not fix it.
+            if let Ok(Exit::Halted(acc)) = vm_run(&copy) {
                 return Some(acc);
             }
         }
@@ -126,22 +164,133 @@ fn part2_search(instructions: &[Instruction]) -> Option<i32> {
     None
 }
 
-pub fn run(path: &str) {
-    let instructions = read_instructions(path).expect("Cannot read instructions");
+/// A program of `size` instructions that loops forever, and halts once a
+/// single `jmp` along its execution path is turned into a `nop`.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let len = size.max(2);
+
+    // Jumps only go forward, so this program halts.
+    let mut program: Vec<(&str, i64)> = (0..len)
+        .map(|index| {
+            let remaining = (len - index) as i64;
+            match rng.range(0, 3) {
+                0 => ("acc", rng.range_i64(-50, 51)),
+                1 => ("jmp", rng.range_i64(1, remaining.min(10) + 1)),
+                _ => ("nop", rng.range_i64(-(index as i64), remaining + 1)),
+            }
+        })
+        .collect();
+
+    let mut path = Vec::new();
+    let mut pc = 0;
+    while pc < len {
+        path.push(pc);
+        pc = match program[pc] {
+            ("jmp", offset) => pc + offset as usize,
+            _ => pc + 1,
+        };
+    }
+
+    // Jumping back onto the path from the path loops forever.
+    let corrupted = rng.index(path.len());
+    let target = path[rng.index(corrupted + 1)];
+    let at = path[corrupted];
+    program[at] = ("jmp", target as i64 - at as i64);
+
+    program
+        .iter()
+        .map(|(operation, value)| format!("{} {:+}\n", operation, value))
+        .collect()
+}
+
+pub struct Day8;
 
-    let part1_res = vm_run(&instructions);
-    match part1_res {
-        Ok(acc) => {
-            println!("day8 part1: {} program halted", acc);
+impl Solution for Day8 {
+    const DAY: u32 = 8;
+
+    type Input = Vec<Instruction>;
+    type Answer1 = i32;
+    type Answer2 = i32;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_instructions(content)
+    }
+
+    /// The accumulator right before the infinite loop, no solution if the program halts.
+    fn part1(instructions: &Self::Input) -> AdventResult<i32> {
+        match vm_run(instructions)? {
+            Exit::Looped(acc) => Ok(acc),
+            Exit::Halted(_) => Err(AdventError::NoSolution),
         }
-        Err(acc) => {
-            println!("day8 part1: {} infinite loop", acc);
+    }
+
+    fn part2(instructions: &Self::Input) -> AdventResult<i32> {
+        part2_search(instructions).ok_or(AdventError::NoSolution)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6
+";
+
+    #[test]
+    fn example() {
+        let instructions = parse_instructions(EXAMPLE).unwrap();
+        assert_eq!(Day8::part1(&instructions).unwrap(), 5);
+        assert_eq!(Day8::part2(&instructions).unwrap(), 8);
+    }
+
+    #[test]
+    fn malformed_instruction() {
+        let err = expect_parse_error(parse_instructions("nop +0\nmul +2\n"));
+        assert_eq!((err.line, err.column), (Some(2), Some(1)));
+
+        let err = expect_parse_error(parse_instruction("acc"));
+        assert_eq!(err.text, "acc");
+
+        let err = expect_parse_error(parse_instruction("acc x1"));
+        assert_eq!(err.column, Some(5));
+    }
+
+    #[test]
+    fn jump_before_start() {
+        let instructions = parse_instructions("nop +0\njmp -2\n").unwrap();
+        match Day8::part1(&instructions) {
+            Err(AdventError::Solver(message)) => {
+                assert_eq!(message, "jmp -2 at line 2 leaves the program")
+            }
+            other => panic!("expected a solver error, got {:?}", other),
         }
+        assert_eq!(Day8::part2(&instructions).unwrap(), 0);
+
+        let instructions = parse_instructions("acc +2147483647\nacc +1\n").unwrap();
+        assert!(matches!(
+            Day8::part1(&instructions),
+            Err(AdventError::Overflow(_))
+        ));
     }
 
-    if let Some(acc) = part2_search(&instructions) {
-        println!("day8 part2: {}", acc);
-    } else {
-        println!("day8 part2: no solution found");
+    #[test]
+    fn run_then_step() {
+        let instructions = parse_instructions("acc +3\nnop +0\n").unwrap();
+        let mut vm = VM::new(&instructions);
+        assert_eq!(vm.run().unwrap(), Exit::Halted(3));
+        assert!(matches!(vm.step(), Err(AdventError::Solver(_))));
     }
 }
@@ -1,28 +1,23 @@
 use crate::common::*;
+use crate::solution::Solution;
 
-fn parse_input_numbers(path: &str) -> AdventResult<Vec<u64>> {
-    let content = std::fs::read_to_string(path)?;
-
-    let mut numbers = Vec::new();
-    for line in content.lines() {
-        let value = line.parse()?;
-        numbers.push(value);
-    }
-    Ok(numbers)
+pub fn parse_numbers(content: &str) -> AdventResult<Vec<u64>> {
+    parse_lines(content)
 }
 
-const WINDOW_LEN: usize = 25;
+pub(crate) const WINDOW_LEN: usize = 25;
 
-fn find_weakness(numbers: &[u64]) -> Option<u64> {
-    for window in numbers.windows(WINDOW_LEN + 1) {
-        let current = window[WINDOW_LEN];
-        let prelude = &window[0..WINDOW_LEN];
-        assert_eq!(prelude.len(), WINDOW_LEN);
+pub(crate) fn find_weakness(numbers: &[u64], window_len: usize) -> Option<u64> {
+    for window in numbers.windows(window_len + 1) {
+        let current = window[window_len];
+        let prelude = &window[0..window_len];
+        assert_eq!(prelude.len(), window_len);
 
         let mut found_pair = false;
         for a in prelude {
             for b in prelude {
-                if a != b && a + b == current {
+                // A sum past u64::MAX cannot equal `current`.
+                if a != b && a.checked_add(*b) == Some(current) {
                     found_pair = true;
                 }
             }
@@ -35,11 +30,13 @@ fn find_weakness(numbers: &[u64]) -> Option<u64> {
     None
 }
 
-fn find_consecutive_sum(numbers: &[u64], weakness: u64) -> Option<&[u64]> {
+pub(crate) fn find_consecutive_sum(numbers: &[u64], weakness: u64) -> Option<&[u64]> {
     for len in 2..(numbers.len() + 1) {
         for window in numbers.windows(len) {
-            let sum: u64 = window.iter().sum();
-            if sum == weakness {
+            let sum = window
+                .iter()
+                .try_fold(0u64, |sum, &number| sum.checked_add(number));
+            if sum == Some(weakness) {
                 return Some(window);
             }
         }
@@ -47,12 +44,13 @@ fn find_consecutive_sum(numbers: &[u64], weakness: u64) -> Option<&[u64]> {
     None
 }
 
-fn compute_consec_signature(numbers: &[u64], weakness: u64) -> Option<u64> {
+pub(crate) fn compute_consec_signature(numbers: &[u64], weakness: u64) -> Option<u64> {
     let sum_window = find_consecutive_sum(numbers, weakness);
     sum_window.and_then(|window| {
         let min = window.iter().min();
         let max = window.iter().max();
 
+        // Both are part of the window, so they add up to at most `weakness`.
         match (min, max) {
             (Some(min), Some(max)) => Some(min + max),
             _ => None,
@@ -60,11 +58,132 @@ fn compute_consec_signature(numbers: &[u64], weakness: u64) -> Option<u64> {
     })
 }
 
-pub fn run(path: &str) {
-    let numbers = parse_input_numbers(path).expect("Cannot read input numbers");
-    let weakness = find_weakness(&numbers).expect("No weakness found");
-    println!("day9 part1: {}", weakness);
+/// Values must stay far from `u64::MAX` since the solvers add them up.
+const GENERATED_LIMIT: u64 = 1 << 50;
+
+fn is_pair_sum(window: &[u64], value: u64) -> bool {
+    window
+        .iter()
+        .any(|&a| window.iter().any(|&b| a != b && a + b == value))
+}
+
+/// A stream of up to `size` numbers, each the sum of two of the previous
+/// `WINDOW_LEN`, except one that is instead the sum of a contiguous run.
+///
+/// Such a stream grows exponentially, so it stops early once values would
+/// reach 2^50, after several hundred numbers.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    let mut numbers: Vec<u64> = (1..=WINDOW_LEN as u64).collect();
+    rng.shuffle(&mut numbers);
+
+    'stream: while numbers.len() < size.max(WINDOW_LEN + 2) {
+        let window = &numbers[numbers.len() - WINDOW_LEN..];
+        let (a, b) = loop {
+            let (a, b) = (*rng.choose(window), *rng.choose(window));
+            if a != b {
+                break (a, b);
+            }
+        };
+        if a + b > GENERATED_LIMIT {
+            break 'stream;
+        }
+        numbers.push(a + b);
+    }
+
+    let len = numbers.len();
+    let weakness_index = rng.range(((WINDOW_LEN + len) / 2) as u64, len as u64) as usize;
+    let window = &numbers[weakness_index - WINDOW_LEN..weakness_index];
+    let weakness = loop {
+        let run_len = rng.range(2, 18) as usize;
+        let start = rng.index(weakness_index + 1 - run_len);
+        let sum = numbers[start..start + run_len].iter().sum();
+        if !is_pair_sum(window, sum) {
+            break sum;
+        }
+    };
+    numbers[weakness_index] = weakness;
+
+    numbers
+        .iter()
+        .map(|number| format!("{}\n", number))
+        .collect()
+}
+
+pub struct Day9;
+
+impl Solution for Day9 {
+    const DAY: u32 = 9;
+
+    type Input = Vec<u64>;
+    type Answer1 = u64;
+    type Answer2 = u64;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_numbers(content)
+    }
+
+    fn part1(numbers: &Self::Input) -> AdventResult<u64> {
+        find_weakness(numbers, WINDOW_LEN).ok_or(AdventError::NoSolution)
+    }
+
+    fn part2(numbers: &Self::Input) -> AdventResult<u64> {
+        let weakness = find_weakness(numbers, WINDOW_LEN).ok_or(AdventError::NoSolution)?;
+        compute_consec_signature(numbers, weakness).ok_or(AdventError::NoSolution)
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+35
+20
+15
+25
+47
+40
+62
+55
+65
+95
+102
+117
+150
+182
+127
+219
+299
+277
+309
+576
+";
+
+    #[test]
+    fn example() {
+        let numbers = parse_numbers(EXAMPLE).unwrap();
+        let weakness = find_weakness(&numbers, 5).unwrap();
+        assert_eq!(weakness, 127);
+        assert_eq!(compute_consec_signature(&numbers, weakness), Some(62));
+    }
+
+    #[test]
+    fn huge_numbers() {
+        let numbers = parse_numbers(&format!("{}\n", u64::MAX).repeat(WINDOW_LEN + 1)).unwrap();
+        assert_eq!(Day9::part1(&numbers).unwrap(), u64::MAX);
+        assert!(matches!(
+            Day9::part2(&numbers),
+            Err(AdventError::NoSolution)
+        ));
+    }
 
-    let signature = compute_consec_signature(&numbers, weakness).expect("No signature found");
-    println!("day9 part2: {}", signature);
+    #[test]
+    fn malformed_line() {
+        let err = expect_parse_error(parse_numbers("35\n-20\n"));
+        assert_eq!(err.line, Some(2));
+    }
 }
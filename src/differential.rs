@@ -0,0 +1,219 @@
+use crate::common::*;
+use crate::{day10, day13, day7};
+use std::fmt;
+use std::ops::Range;
+
+/// Two implementations of the same answer: a slow one that is obviously
+/// right, and the one the solution uses. Both take the raw input text.
+pub struct Pair {
+    pub name: &'static str,
+    /// Size passed to `generate`, small enough for the naive side.
+    pub size: usize,
+    pub generate: fn(&mut Rng, usize) -> String,
+    pub naive: fn(&str) -> AdventResult<String>,
+    pub fast: fn(&str) -> AdventResult<String>,
+}
+
+/// An input on which both sides of a pair disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub pair: &'static str,
+    pub seed: u64,
+    pub naive: String,
+    pub fast: String,
+    pub input: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} (seed {}): naive gave {}, fast gave {}",
+            self.pair, self.seed, self.naive, self.fast
+        )?;
+        write!(f, "minimized input:\n{}", self.input)
+    }
+}
+
+fn outcome(solver: fn(&str) -> AdventResult<String>, input: &str) -> String {
+    match solver(input) {
+        Ok(answer) => answer,
+        Err(err) => format!("error ({})", err),
+    }
+}
+
+fn disagree(pair: &Pair, input: &str) -> bool {
+    outcome(pair.naive, input) != outcome(pair.fast, input)
+}
+
+/// Removes as many of `items` as possible while `fails` still holds, trying
+/// halves, then quarters and so on (a simplified delta debugging).
+fn ddmin(mut items: Vec<String>, fails: impl Fn(&[String]) -> bool) -> Vec<String> {
+    let mut chunk = items.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < items.len() {
+            let end = (start + chunk).min(items.len());
+            let candidate: Vec<String> = items[..start]
+                .iter()
+                .chain(&items[end..])
+                .cloned()
+                .collect();
+            if fails(&candidate) {
+                items = candidate;
+            } else {
+                start = end;
+            }
+        }
+        chunk /= 2;
+    }
+    items
+}
+
+/// Shrinks `input` while `fails` holds: first whole lines, then the
+/// comma-separated fields of each remaining line.
+pub fn minimize(input: &str, fails: impl Fn(&str) -> bool) -> String {
+    let join =
+        |lines: &[String]| -> String { lines.iter().map(|line| format!("{}\n", line)).collect() };
+
+    let lines: Vec<String> = input.lines().map(str::to_owned).collect();
+    let mut lines = ddmin(lines, |lines| fails(&join(lines)));
+
+    for index in 0..lines.len() {
+        let fields: Vec<String> = lines[index].split(',').map(str::to_owned).collect();
+        let fields = ddmin(fields, |fields| {
+            let mut candidate = lines.clone();
+            candidate[index] = fields.join(",");
+            fails(&join(&candidate))
+        });
+        lines[index] = fields.join(",");
+    }
+
+    join(&lines)
+}
+
+/// Runs both sides of `pair` on an input generated from each seed and
+/// returns the first disagreement, minimized.
+pub fn check(pair: &Pair, seeds: Range<u64>) -> Option<Mismatch> {
+    for seed in seeds {
+        let input = (pair.generate)(&mut Rng::new(seed), pair.size);
+        if disagree(pair, &input) {
+            let input = minimize(&input, |candidate| disagree(pair, candidate));
+            return Some(Mismatch {
+                pair: pair.name,
+                seed,
+                naive: outcome(pair.naive, &input),
+                fast: outcome(pair.fast, &input),
+                input,
+            });
+        }
+    }
+    None
+}
+
+/// A schedule small enough for `part2_raw` to scan: a few ids below 30, or
+/// now and then no id at all.
+fn small_schedule(rng: &mut Rng, size: usize) -> String {
+    let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+    let slots = size.clamp(1, 8);
+    if rng.chance(10) {
+        return format!("{}\n{}\n", rng.range(0, 1000), vec!["x"; slots].join(","));
+    }
+
+    // `part2_raw` scans multiples of the first slot, which must hold an id.
+    let mut ids: Vec<Option<u32>> = vec![None; slots];
+    ids[0] = Some(*rng.choose(&primes));
+    for _ in 0..rng.range(0, 3) {
+        let prime = *rng.choose(&primes);
+        if !ids.contains(&Some(prime)) {
+            ids[rng.index(slots)] = Some(prime);
+        }
+    }
+
+    let ids: Vec<String> = ids
+        .iter()
+        .map(|id| id.map_or_else(|| "x".to_owned(), |id| id.to_string()))
+        .collect();
+    format!("{}\n{}\n", rng.range(0, 1000), ids.join(","))
+}
+
+pub static PAIRS: &[Pair] = &[
+    Pair {
+        name: "day7 part1",
+        size: 40,
+        generate: day7::generate_input,
+        naive: |content| Ok(day7::part1_count_raw(&day7::parse_rules(content)?)?.to_string()),
+        fast: |content| {
+            let rules = day7::parse_rules(content)?;
+            Ok(day7::part1_count(&day7::build_parent_tree(&rules)).to_string())
+        },
+    },
+    Pair {
+        name: "day10 part2",
+        size: 15,
+        generate: day10::generate_input,
+        naive: |content| {
+            let chain = day10::build_chain(day10::parse_ratings(content)?);
+            Ok(day10::count_part2_raw(&chain).to_string())
+        },
+        fast: |content| {
+            let chain = day10::build_chain(day10::parse_ratings(content)?);
+            day10::count_part2(&chain)
+                .map(|count| count.to_string())
+                .ok_or(AdventError::NoSolution)
+        },
+    },
+    Pair {
+        name: "day13 part2",
+        size: 8,
+        generate: small_schedule,
+        naive: |content| day13::part2_raw(&day13::parse_input(content)?).map(|ts| ts.to_string()),
+        fast: |content| day13::part2(&day13::parse_input(content)?).map(|ts| ts.to_string()),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_agree() {
+        for pair in PAIRS {
+            if let Some(mismatch) = check(pair, 0..50) {
+                panic!("{}", mismatch);
+            }
+        }
+    }
+
+    #[test]
+    fn reports_minimized_mismatch() {
+        let pair = Pair {
+            name: "max",
+            size: 50,
+            generate: |rng, size| {
+                (0..size)
+                    .map(|_| format!("{},{}\n", rng.range(0, 100), rng.range(0, 100)))
+                    .collect()
+            },
+            naive: |content| {
+                let values: Vec<u64> = content
+                    .split(&[',', '\n'][..])
+                    .filter(|value| !value.is_empty())
+                    .map(|value| value.parse().unwrap())
+                    .collect();
+                Ok(values.iter().max().copied().unwrap_or(0).to_string())
+            },
+            // Wrong on purpose: ignores everything past the first field.
+            fast: |content| {
+                let first = content.split(&[',', '\n'][..]).next();
+                let first = first.filter(|first| !first.is_empty()).unwrap_or("0");
+                Ok(first.to_owned())
+            },
+        };
+
+        let mismatch = check(&pair, 0..10).unwrap();
+        assert_eq!(mismatch.seed, 0);
+        assert_eq!(mismatch.input.lines().count(), 1);
+        assert_eq!(mismatch.input.matches(',').count(), 1);
+    }
+}
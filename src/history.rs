@@ -0,0 +1,319 @@
+use crate::bench::{DayBench, Phase};
+use crate::common::*;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_HISTORY_PATH: &str = "bench_history.csv";
+
+const HEADER: &str = "commit,date,day,phase,iterations,min_ns,median_ns,max_ns,total_ns";
+
+/// One `bench` invocation: the commit it measured and when it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunId {
+    pub commit: String,
+    pub date: String,
+}
+
+impl RunId {
+    /// Identifies a run of the current checkout, right now.
+    pub fn current() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        RunId {
+            commit: current_commit(),
+            date: format_utc(seconds),
+        }
+    }
+}
+
+impl std::fmt::Display for RunId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.commit, self.date)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub run: RunId,
+    pub day: u32,
+    pub phase: String,
+    pub iterations: usize,
+    pub min_ns: u128,
+    pub median_ns: u128,
+    pub max_ns: u128,
+    pub total_ns: u128,
+}
+
+fn current_commit() -> String {
+    std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_owned())
+        .filter(|commit| !commit.is_empty())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Formats seconds since the epoch as an ISO 8601 UTC date.
+fn format_utc(seconds: u64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (seconds / 86400) as i64;
+    let secs_of_day = seconds % 86400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Appends `results` to the history file at `path`, creating it if needed.
+pub fn append(path: &str, run: &RunId, results: &[DayBench]) -> AdventResult<()> {
+    let is_new = std::fs::metadata(path).is_err();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| AdventError::from(err).in_file(path))?;
+
+    let mut out = String::new();
+    if is_new {
+        out.push_str(HEADER);
+        out.push('\n');
+    }
+    for result in results {
+        for &phase in &Phase::ALL {
+            let timings = result.phase(phase);
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                run.commit,
+                run.date,
+                result.day,
+                phase.name(),
+                result.iterations,
+                timings.min().as_nanos(),
+                timings.median().as_nanos(),
+                timings.max().as_nanos(),
+                timings.total().as_nanos()
+            ));
+        }
+    }
+
+    file.write_all(out.as_bytes())
+        .map_err(|err| AdventError::from(err).in_file(path))
+}
+
+fn parse_entry(line: &str) -> AdventResult<HistoryEntry> {
+    let fields: Vec<_> = line.split(',').collect();
+    if fields.len() != 9 {
+        return Err(AdventError::parse(
+            "Expected 9 comma separated fields",
+            line,
+        ));
+    }
+
+    Ok(HistoryEntry {
+        run: RunId {
+            commit: fields[0].to_owned(),
+            date: fields[1].to_owned(),
+        },
+        day: parse_token(line, fields[2])?,
+        phase: fields[3].to_owned(),
+        iterations: parse_token(line, fields[4])?,
+        min_ns: parse_token(line, fields[5])?,
+        median_ns: parse_token(line, fields[6])?,
+        max_ns: parse_token(line, fields[7])?,
+        total_ns: parse_token(line, fields[8])?,
+    })
+}
+
+pub fn parse(content: &str) -> AdventResult<Vec<HistoryEntry>> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && *line != HEADER)
+        .map(|(index, line)| parse_entry(line).map_err(|err| err.at_line(index + 1)))
+        .collect()
+}
+
+pub fn read(path: &str) -> AdventResult<Vec<HistoryEntry>> {
+    read_and_parse(path, parse)
+}
+
+/// Distinct runs in the order they were recorded.
+pub fn runs(entries: &[HistoryEntry]) -> Vec<RunId> {
+    let mut runs: Vec<RunId> = Vec::new();
+    for entry in entries {
+        if runs.last() != Some(&entry.run) {
+            runs.push(entry.run.clone());
+        }
+    }
+    runs
+}
+
+/// Latest recorded run whose commit starts with `commit`.
+pub fn find_run(entries: &[HistoryEntry], commit: &str) -> Option<RunId> {
+    runs(entries)
+        .into_iter()
+        .rev()
+        .find(|run| run.commit.starts_with(commit))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub day: u32,
+    pub phase: String,
+    pub baseline_ns: u128,
+    pub current_ns: u128,
+    pub change_percent: f64,
+    pub regressed: bool,
+}
+
+/// Compares the median of every day/phase measured in both runs. A phase
+/// regressed when it got slower by more than `threshold_percent`; a phase
+/// that took no time in the baseline regresses as soon as it takes any.
+pub fn compare(
+    entries: &[HistoryEntry],
+    baseline: &RunId,
+    current: &RunId,
+    threshold_percent: f64,
+) -> Vec<Comparison> {
+    let mut comparisons = Vec::new();
+
+    for entry in entries.iter().filter(|entry| &entry.run == current) {
+        let base = entries.iter().find(|base| {
+            &base.run == baseline && base.day == entry.day && base.phase == entry.phase
+        });
+
+        if let Some(base) = base {
+            let change_percent = if base.median_ns == 0 {
+                // Any time at all is an unbounded slowdown from nothing.
+                if entry.median_ns == 0 {
+                    0.0
+                } else {
+                    f64::INFINITY
+                }
+            } else {
+                (entry.median_ns as f64 - base.median_ns as f64) / base.median_ns as f64 * 100.0
+            };
+            comparisons.push(Comparison {
+                day: entry.day,
+                phase: entry.phase.clone(),
+                baseline_ns: base.median_ns,
+                current_ns: entry.median_ns,
+                change_percent,
+                regressed: change_percent > threshold_percent,
+            });
+        }
+    }
+
+    comparisons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HISTORY: &str = "\
+commit,date,day,phase,iterations,min_ns,median_ns,max_ns,total_ns
+abc1234,2020-12-01T10:00:00Z,1,parse,3,90,100,110,300
+abc1234,2020-12-01T10:00:00Z,1,part1,3,180,200,220,600
+abc1234,2020-12-01T10:00:00Z,1,part2,3,0,0,0,0
+def5678,2020-12-02T10:00:00Z,1,parse,3,100,150,200,450
+def5678,2020-12-02T10:00:00Z,1,part1,3,150,190,230,570
+def5678,2020-12-02T10:00:00Z,1,part2,3,5,10,15,30
+def5678,2020-12-02T10:00:00Z,2,parse,3,1,1,1,3
+";
+
+    fn run(commit: &str, date: &str) -> RunId {
+        RunId {
+            commit: commit.to_owned(),
+            date: date.to_owned(),
+        }
+    }
+
+    #[test]
+    fn utc_dates() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(1_582_979_696), "2020-02-29T12:34:56Z");
+        assert_eq!(format_utc(951_868_799), "2000-02-29T23:59:59Z");
+        assert_eq!(format_utc(951_868_800), "2000-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn runs_in_order() {
+        let entries = parse(HISTORY).unwrap();
+        assert_eq!(entries.len(), 7);
+        assert_eq!(
+            runs(&entries),
+            vec![
+                run("abc1234", "2020-12-01T10:00:00Z"),
+                run("def5678", "2020-12-02T10:00:00Z"),
+            ]
+        );
+
+        assert_eq!(
+            find_run(&entries, "abc"),
+            Some(run("abc1234", "2020-12-01T10:00:00Z"))
+        );
+        assert_eq!(find_run(&entries, "123"), None);
+        assert_eq!(find_run(&[], "abc"), None);
+    }
+
+    #[test]
+    fn compare_medians() {
+        let entries = parse(HISTORY).unwrap();
+        let runs = runs(&entries);
+        let comparisons = compare(&entries, &runs[0], &runs[1], 10.0);
+
+        // Day 2 has no baseline and is left out.
+        let deltas: Vec<_> = comparisons
+            .iter()
+            .map(|c| {
+                (
+                    c.phase.as_str(),
+                    c.baseline_ns,
+                    c.current_ns,
+                    c.change_percent,
+                    c.regressed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            deltas,
+            vec![
+                ("parse", 100, 150, 50.0, true),
+                ("part1", 200, 190, -5.0, false),
+                ("part2", 0, 10, f64::INFINITY, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_history() {
+        let err = expect_parse_error(parse("abc,2020-12-01T10:00:00Z,1,parse,3\n"));
+        assert_eq!(err.line, Some(1));
+
+        let err = expect_parse_error(parse(&format!("{}\nabc,d,x,parse,3,1,1,1,3\n", HEADER)));
+        assert_eq!((err.line, err.text.as_str()), (Some(2), "x"));
+    }
+}
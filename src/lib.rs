@@ -0,0 +1,36 @@
+//! Advent of Code 2020 solutions.
+//!
+//! Every day module exposes its input parsers and implements
+//! [`solution::Solution`] so the days can be driven uniformly through
+//! [`solution::SOLUTIONS`]; the solvers behind it stay crate-private. The
+//! reusable pieces are re-exported here, next to
+//! [`common::split_blocks`].
+
+pub mod answers;
+pub mod bench;
+pub mod common;
+pub mod day1;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+#[cfg(test)]
+mod differential;
+pub mod history;
+pub mod report;
+pub mod scaffold;
+pub mod solution;
+
+pub use day11::Map;
+pub use day14::Memory;
+pub use day3::TreeMap;
+pub use day8::VM;
@@ -1,36 +1,486 @@
-#![allow(dead_code)]
-
-mod common;
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
+mod cli;
+
+use aoc2020::answers::{self, Answers, Verdict};
+use aoc2020::bench;
+use aoc2020::common::{read_and_parse, read_source, AdventError, AdventResult, Rng};
+use aoc2020::history::{self, RunId};
+use aoc2020::report::{self, PartResult, Status};
+use aoc2020::scaffold;
+use aoc2020::solution::{self, DynSolution, SOLUTIONS};
+use aoc2020::{day1, day2};
+use cli::{
+    BenchOptions, Command, CompareOptions, DaySelection, ExpenseMode, GenerateOptions,
+    OutputFormat, RunOptions, VerifyOptions,
+};
+use std::path::Path;
+
+/// Returns whether every selected part ran without error or timeout.
+fn run(options: &RunOptions) -> AdventResult<bool> {
+    if options.expense_query() {
+        return run_expense_query(options);
+    }
+    if options.audit {
+        return run_password_audit(options);
+    }
+    if !options.policies.is_empty() {
+        return run_policy_query(options);
+    }
+    if let Some(jobs) = options.jobs {
+        return run_parallel(options, jobs);
+    }
+
+    let mut success = true;
+    for solution in selected_solutions(options.days)? {
+        let path = options.input_path(solution.day());
+        for result in report::run_solution(solution, &path, options.part, options.timeout) {
+            match options.format {
+                OutputFormat::Json => println!("{}", result.to_json()),
+                _ => println!("{}", result),
+            }
+            success &= succeeded(&result);
+        }
+    }
+    Ok(success)
+}
+
+/// Day 1 with another entry count or target than the puzzle's. Without
+/// `--k`, the selected parts keep their entry counts of 2 and 3.
+fn run_expense_query(options: &RunOptions) -> AdventResult<bool> {
+    let values = read_and_parse(&options.input_path(1), day1::parse_input)?;
+    let target = options.target.unwrap_or(day1::TARGET);
+    let counts = match options.k {
+        Some(k) => vec![k],
+        None => [(2, options.part.part1()), (3, options.part.part2())]
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(k, _)| *k)
+            .collect(),
+    };
+
+    let json = options.format == OutputFormat::Json;
+    let mut success = true;
+    for k in counts {
+        match options.expense_mode {
+            ExpenseMode::First => success &= print_expense_product(&values, k, target, json),
+            ExpenseMode::List => {
+                let mut found = false;
+                for indices in day1::k_sums(&values, k, target) {
+                    success &= print_expense_solution(&values, &indices, target, json);
+                    found = true;
+                }
+                if !found {
+                    // No exact sum: report the closest one.
+                    success &= print_expense_product(&values, k, target, json);
+                }
+            }
+            ExpenseMode::Count => {
+                let count = day1::count_k_sums(&values, k, target);
+                if json {
+                    println!(
+                        "{{\"day\":1,\"k\":{},\"target\":{},\"count\":{}}}",
+                        k, target, count
+                    );
+                } else {
+                    let noun = if count == 1 { "solution" } else { "solutions" };
+                    println!(
+                        "day1 {} entries summing to {}: {} {}",
+                        k, target, count, noun
+                    );
+                }
+            }
+        }
+    }
+    Ok(success)
+}
+
+fn json_list<T: ToString>(items: impl Iterator<Item = T>) -> String {
+    format!(
+        "[{}]",
+        items
+            .map(|item| item.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// Prints the product of `k` entries summing to `target`, or the nearest
+/// sum when there is none. Returns whether it did not fail.
+fn print_expense_product(values: &[i64], k: usize, target: i64, json: bool) -> bool {
+    let (status, text) = match day1::expense_product(values, k, target) {
+        Ok(product) => (Status::Solved, product.to_string()),
+        Err(AdventError::NoSolution) => (Status::NoSolution, "no solution found".to_owned()),
+        Err(err) => (Status::Error, err.to_string()),
+    };
+    let closest = match status {
+        Status::NoSolution => day1::closest_k_sum(values, k, target),
+        _ => None,
+    };
+
+    if json {
+        let answer = match status {
+            Status::Solved => report::json_string(&text),
+            _ => "null".to_owned(),
+        };
+        let closest = closest.map_or("null".to_owned(), |(sum, indices)| {
+            format!(
+                "{{\"sum\":{},\"entries\":{}}}",
+                sum,
+                json_list(indices.iter().map(|&index| values[index]))
+            )
+        });
+        println!(
+            "{{\"day\":1,\"k\":{},\"target\":{},\"status\":{},\"answer\":{},\"closest\":{}}}",
+            k,
+            target,
+            report::json_string(status.name()),
+            answer,
+            closest
+        );
+    } else {
+        let closest = closest.map_or(String::new(), |(sum, indices)| {
+            format!(
+                ", closest sum is {} ({})",
+                sum,
+                day1::describe(values, &indices)
+            )
+        });
+        println!(
+            "day1 {} entries summing to {}: {}{}",
+            k, target, text, closest
+        );
+    }
+    status != Status::Error
+}
+
+/// Prints one set of entries summing to `target` and their product.
+fn print_expense_solution(values: &[i64], indices: &[usize], target: i64, json: bool) -> bool {
+    let product = day1::product_of(values, indices);
+    if json {
+        println!(
+            "{{\"day\":1,\"k\":{},\"target\":{},\"lines\":{},\"entries\":{},\"product\":{}}}",
+            indices.len(),
+            target,
+            json_list(indices.iter().map(|index| index + 1)),
+            json_list(indices.iter().map(|&index| values[index])),
+            product.as_ref().map_or("null".to_owned(), |product| {
+                report::json_string(&product.to_string())
+            })
+        );
+    } else {
+        let lines: Vec<String> = indices
+            .iter()
+            .map(|index| (index + 1).to_string())
+            .collect();
+        let product = match &product {
+            Ok(product) => product.to_string(),
+            Err(err) => err.to_string(),
+        };
+        println!(
+            "day1 lines {}: {} = {}, product {}",
+            lines.join(", "),
+            day1::describe(values, indices),
+            target,
+            product
+        );
+    }
+    product.is_ok()
+}
+
+/// Counts the day 2 lines each `--policy` accepts.
+fn run_policy_query(options: &RunOptions) -> AdventResult<bool> {
+    let rows = read_and_parse(&options.input_path(2), day2::parse_password_file)?;
+    for spec in &options.policies {
+        let policy = day2::policy_by_name(spec)?;
+        let valid = day2::count_valid(&rows, policy.as_ref());
+        match options.format {
+            OutputFormat::Json => println!(
+                "{{\"day\":2,\"policy\":{},\"valid\":{},\"total\":{}}}",
+                report::json_string(&policy.name()),
+                valid,
+                rows.len()
+            ),
+            _ => println!(
+                "day2 policy {}: {} of {} lines valid",
+                policy.name(),
+                valid,
+                rows.len()
+            ),
+        }
+    }
+    Ok(true)
+}
+
+/// Audits every day 2 line against each `--policy`, the puzzle's two by
+/// default. Returns whether no line was malformed.
+fn run_password_audit(options: &RunOptions) -> AdventResult<bool> {
+    let path = options.input_path(2);
+    let content = read_source(&path)?;
+    let specs = match options.policies.as_slice() {
+        [] => vec!["count".to_owned(), "xor".to_owned()],
+        specs => specs.to_vec(),
+    };
+
+    let mut audits = Vec::new();
+    for spec in &specs {
+        let policy = day2::policy_by_name(spec)?;
+        audits.push((policy.name(), day2::audit(&content, policy.as_ref())));
+    }
+
+    match options.format {
+        OutputFormat::Csv => print!("{}", day2::audit_to_csv(&audits)),
+        OutputFormat::Json => {
+            for (policy, entries) in &audits {
+                for entry in entries {
+                    let reason = entry
+                        .reason
+                        .as_deref()
+                        .map_or("null".to_owned(), report::json_string);
+                    println!(
+                        "{{\"day\":2,\"policy\":{},\"line\":{},\"outcome\":{},\"reason\":{}}}",
+                        report::json_string(policy),
+                        entry.line,
+                        report::json_string(entry.outcome.name()),
+                        reason
+                    );
+                }
+            }
+        }
+        OutputFormat::Text => {
+            for (policy, entries) in &audits {
+                for entry in entries {
+                    println!("day2 {} {}", policy, entry);
+                }
+            }
+        }
+    }
+
+    Ok(audits
+        .iter()
+        .flat_map(|(_, entries)| entries)
+        .all(|entry| entry.outcome != day2::Outcome::Malformed))
+}
+
+/// Runs the selected days on `jobs` threads and prints one summary once all
+/// of them are done.
+fn run_parallel(options: &RunOptions, jobs: usize) -> AdventResult<bool> {
+    let work: Vec<_> = selected_solutions(options.days)?
+        .into_iter()
+        .map(|solution| (solution, options.input_path(solution.day())))
+        .collect();
+    let results = report::run_parallel(&work, options.part, options.timeout, jobs);
+
+    match options.format {
+        OutputFormat::Json => {
+            for result in &results {
+                println!("{}", result.to_json());
+            }
+        }
+        _ => print!("{}", report::to_table(&results)),
+    }
+    Ok(results.iter().all(succeeded))
+}
+
+fn succeeded(result: &PartResult) -> bool {
+    !matches!(result.status, Status::Error | Status::Timeout)
+}
+
+/// Returns whether every checked answer matched.
+fn verify(options: &VerifyOptions) -> AdventResult<bool> {
+    let answers = Answers::read(&options.answers)?;
+    let solutions = selected_solutions(options.days)?;
+    let checks = answers::verify(&answers, &solutions, options.timeout);
+    let (mut passed, mut failed, mut missing) = (0, 0, 0);
+    for check in &checks {
+        let status = match &check.verdict {
+            Verdict::Pass => {
+                passed += 1;
+                "pass".to_owned()
+            }
+            Verdict::Fail { expected, actual } => {
+                failed += 1;
+                format!("FAIL (expected {}, got {})", expected, actual)
+            }
+            Verdict::Missing { actual } => {
+                missing += 1;
+                format!("missing (got {})", actual)
+            }
+            Verdict::Error(err) => {
+                failed += 1;
+                format!("ERROR ({})", err)
+            }
+        };
+        println!(
+            "day{} {} part{}: {}",
+            check.day, check.input, check.part, status
+        );
+    }
+
+    println!("{} passed, {} failed, {} missing", passed, failed, missing);
+    Ok(failed == 0)
+}
+
+fn selected_solutions(days: DaySelection) -> AdventResult<Vec<&'static dyn DynSolution>> {
+    match days {
+        DaySelection::All => Ok(SOLUTIONS.to_vec()),
+        DaySelection::Single(day) => Ok(vec![solution::find_solution(day)?]),
+    }
+}
+
+fn bench(options: &BenchOptions) -> AdventResult<()> {
+    let mut results = Vec::new();
+    for solution in selected_solutions(options.days)? {
+        let path = cli::default_input_path(solution.day());
+        results.push(bench::bench_solution(solution, &path, options.iterations)?);
+    }
+
+    if options.save {
+        history::append(&options.history, &RunId::current(), &results)?;
+    }
+
+    let report = match options.format {
+        OutputFormat::Text => bench::to_table(&results),
+        OutputFormat::Csv => bench::to_csv(&results),
+        OutputFormat::Json => bench::to_json(&results),
+    };
+
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, report).map_err(|err| AdventError::from(err).in_file(path))
+        }
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+fn generate(options: &GenerateOptions) -> AdventResult<()> {
+    let solution = solution::find_solution(options.day)?;
+    let input = solution.generate_input(&mut Rng::new(options.seed), options.size);
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, input).map_err(|err| AdventError::from(err).in_file(path))
+        }
+        None => {
+            print!("{}", input);
+            Ok(())
+        }
+    }
+}
+
+fn new_day(day: u32) -> AdventResult<()> {
+    for path in scaffold::scaffold(Path::new("."), day)? {
+        println!("wrote {}", path);
+    }
+    Ok(())
+}
+
+/// Returns whether no phase regressed.
+fn bench_compare(options: &CompareOptions) -> Result<bool, String> {
+    let entries = history::read(&options.history).map_err(|err| err.to_string())?;
+    let runs = history::runs(&entries);
+
+    let find = |commit: &str| {
+        history::find_run(&entries, commit)
+            .ok_or_else(|| format!("no run of commit {} in {}", commit, options.history))
+    };
+    let current = match &options.current {
+        Some(commit) => find(commit)?,
+        None => runs
+            .last()
+            .cloned()
+            .ok_or_else(|| format!("no run recorded in {}", options.history))?,
+    };
+    let baseline = match &options.baseline {
+        Some(commit) => find(commit)?,
+        None => runs
+            .iter()
+            .take_while(|run| **run != current)
+            .last()
+            .cloned()
+            .ok_or_else(|| "no earlier run to compare with".to_owned())?,
+    };
+
+    println!("baseline: {}", baseline);
+    println!("current:  {}", current);
+
+    let comparisons = history::compare(&entries, &baseline, &current, options.threshold);
+    let mut regressions = 0;
+    for comparison in &comparisons {
+        let flag = if comparison.regressed {
+            regressions += 1;
+            "REGRESSED"
+        } else {
+            "ok"
+        };
+        println!(
+            "day{} {}: {:?} -> {:?} ({:+.1}%) {}",
+            comparison.day,
+            comparison.phase,
+            std::time::Duration::from_nanos(comparison.baseline_ns as u64),
+            std::time::Duration::from_nanos(comparison.current_ns as u64),
+            comparison.change_percent,
+            flag
+        );
+    }
+    println!("{} regressions above {}%", regressions, options.threshold);
+    Ok(regressions == 0)
+}
 
 fn main() {
-    /*
-    day1::run("inputs/day1/input.txt");
-    day2::run("inputs/day2/input.txt");
-    day3::run("inputs/day3/input.txt");
-    day4::run("inputs/day4/input.txt");
-    day5::run("inputs/day5/input.txt");
-    day6::run("inputs/day6/input.txt");
-    day7::run("inputs/day7/input.txt");
-    day8::run("inputs/day8/input.txt");
-    day9::run("inputs/day9/input.txt");
-    day10::run("inputs/day10/input.txt");
-    day11::run("inputs/day11/input.txt");
-    day12::run("inputs/day12/input.txt");
-    day13::run("inputs/day13/input.txt");
-    */
-    day14::run("inputs/day14/test.txt");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Run(options) => match run(&options) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        },
+        Command::Bench(options) => {
+            if let Err(err) = bench(&options) {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::Generate(options) => {
+            if let Err(err) = generate(&options) {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::New(day) => {
+            if let Err(err) = new_day(day) {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::BenchCompare(options) => match bench_compare(&options) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        },
+        Command::Verify(options) => match verify(&options) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        },
+    }
 }
@@ -0,0 +1,425 @@
+use crate::common::*;
+use crate::solution::DynSolution;
+use std::any::Any;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Solved,
+    NoSolution,
+    Error,
+    Timeout,
+}
+
+impl Status {
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Solved => "solved",
+            Status::NoSolution => "no solution",
+            Status::Error => "error",
+            Status::Timeout => "timeout",
+        }
+    }
+}
+
+/// Outcome of one part of one day, with how long parsing and solving took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartResult {
+    pub day: u32,
+    pub part: u32,
+    pub status: Status,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    pub parse_time: Duration,
+    pub solve_time: Duration,
+}
+
+impl PartResult {
+    fn new(day: u32, part: u32, answer: AdventResult<String>) -> Self {
+        let (status, answer, error) = match answer {
+            Ok(answer) => (Status::Solved, Some(answer), None),
+            Err(AdventError::NoSolution) => (Status::NoSolution, None, None),
+            Err(err @ AdventError::Timeout(_)) => (Status::Timeout, None, Some(err.to_string())),
+            Err(err) => (Status::Error, None, Some(err.to_string())),
+        };
+        PartResult {
+            day,
+            part,
+            status,
+            answer,
+            error,
+            parse_time: Duration::default(),
+            solve_time: Duration::default(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let optional = |value: &Option<String>| match value {
+            Some(value) => json_string(value),
+            None => "null".to_owned(),
+        };
+        format!(
+            "{{\"day\":{},\"part\":{},\"status\":{},\"answer\":{},\"error\":{},\"parse_ns\":{},\"solve_ns\":{}}}",
+            self.day,
+            self.part,
+            json_string(self.status.name()),
+            optional(&self.answer),
+            optional(&self.error),
+            self.parse_time.as_nanos(),
+            self.solve_time.as_nanos()
+        )
+    }
+}
+
+impl std::fmt::Display for PartResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "day{} part{}: ", self.day, self.part)?;
+        match (&self.answer, &self.error) {
+            (Some(answer), _) => write!(f, "{}", answer),
+            (None, Some(error)) if self.status == Status::Timeout => write!(f, "{}", error),
+            (None, Some(error)) => write!(f, "error: {}", error),
+            (None, None) => write!(f, "no solution found"),
+        }
+    }
+}
+
+/// Quotes and escapes `value` as a JSON string.
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `value` as a CSV field, quoted when it holds a separator or a quote.
+pub fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn selected_parts(parts: PartSelection) -> Vec<u32> {
+    [(1, parts.part1()), (2, parts.part2())]
+        .iter()
+        .filter(|(_, selected)| *selected)
+        .map(|(part, _)| *part)
+        .collect()
+}
+
+fn failed_parts(
+    day: u32,
+    parts: &[u32],
+    err: AdventError,
+    parse_time: Duration,
+) -> Vec<PartResult> {
+    let status = match err {
+        AdventError::Timeout(_) => Status::Timeout,
+        _ => Status::Error,
+    };
+    let error = err.to_string();
+    parts
+        .iter()
+        .map(|&part| PartResult {
+            day,
+            part,
+            status,
+            answer: None,
+            error: Some(error.clone()),
+            parse_time,
+            solve_time: Duration::default(),
+        })
+        .collect()
+}
+
+/// Reads and parses the input at `path` (`-` for stdin) and solves the
+/// selected parts. A read or parse failure is reported as an error on every
+/// selected part; `parse_time` does not include reading. Parsing and solving
+/// share `budget`; once it is spent the remaining parts report a timeout.
+pub fn run_solution(
+    solution: &dyn DynSolution,
+    path: &str,
+    parts: PartSelection,
+    budget: Option<Duration>,
+) -> Vec<PartResult> {
+    let day = solution.day();
+    let selected = selected_parts(parts);
+
+    let content = match read_source(path) {
+        Ok(content) => content,
+        Err(err) => return failed_parts(day, &selected, err, Duration::default()),
+    };
+
+    with_budget(budget, || solve_parts(solution, path, &content, &selected))
+}
+
+fn solve_parts(
+    solution: &dyn DynSolution,
+    path: &str,
+    content: &str,
+    selected: &[u32],
+) -> Vec<PartResult> {
+    let day = solution.day();
+    let start = Instant::now();
+    let input = solution.parse_input(content);
+    let parse_time = start.elapsed();
+
+    let input = match input {
+        Ok(input) => input,
+        Err(err) => {
+            let err = err.in_file(source_name(path));
+            return failed_parts(day, selected, err, parse_time);
+        }
+    };
+
+    selected
+        .iter()
+        .map(|&part| {
+            let start = Instant::now();
+            let answer = check_deadline().and_then(|_| match part {
+                1 => solution.solve_part1(input.as_ref()),
+                _ => solution.solve_part2(input.as_ref()),
+            });
+            PartResult {
+                parse_time,
+                solve_time: start.elapsed(),
+                ..PartResult::new(day, part, answer)
+            }
+        })
+        .collect()
+}
+
+/// The message given to `panic!`, when it is a string.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+/// Like `run_solution`, but a panicking solver is reported as an error on
+/// every selected part instead of unwinding into the caller.
+pub fn run_isolated(
+    solution: &dyn DynSolution,
+    path: &str,
+    parts: PartSelection,
+    budget: Option<Duration>,
+) -> Vec<PartResult> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        run_solution(solution, path, parts, budget)
+    }))
+    .unwrap_or_else(|payload| {
+        let err = AdventError::Solver(format!("panicked: {}", panic_message(&*payload)));
+        failed_parts(
+            solution.day(),
+            &selected_parts(parts),
+            err,
+            Duration::default(),
+        )
+    })
+}
+
+/// Runs each `(solution, input path)` job on a pool of `threads` workers,
+/// each job with its own `budget`. Results keep the order of `jobs`.
+pub fn run_parallel(
+    jobs: &[(&dyn DynSolution, String)],
+    parts: PartSelection,
+    budget: Option<Duration>,
+    threads: usize,
+) -> Vec<PartResult> {
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Vec<PartResult>>> = jobs.iter().map(|_| Mutex::default()).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let (solution, path) = match jobs.get(index) {
+                    Some(job) => job,
+                    None => break,
+                };
+                *slots[index].lock().unwrap() = run_isolated(*solution, path, parts, budget);
+            });
+        }
+    });
+
+    slots
+        .into_iter()
+        .flat_map(|slot| slot.into_inner().unwrap())
+        .collect()
+}
+
+/// Summary of `results` as an aligned table, with the totals at the end.
+pub fn to_table(results: &[PartResult]) -> String {
+    let mut out = format!(
+        "{:>5} {:>5} {:>12} {:>12} {:>12}  {}\n",
+        "day", "part", "status", "parse", "solve", "answer"
+    );
+    for result in results {
+        let answer = match (&result.answer, &result.error) {
+            (Some(answer), _) => answer.as_str(),
+            (None, Some(error)) => error.as_str(),
+            (None, None) => "-",
+        };
+        writeln!(
+            out,
+            "{:>5} {:>5} {:>12} {:>12.1?} {:>12.1?}  {}",
+            result.day,
+            result.part,
+            result.status.name(),
+            result.parse_time,
+            result.solve_time,
+            answer
+        )
+        .unwrap();
+    }
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    let solve_time: Duration = results.iter().map(|r| r.solve_time).sum();
+    writeln!(
+        out,
+        "{} solved, {} without solution, {} errors, {} timeouts, solve time {:.1?}",
+        count(Status::Solved),
+        count(Status::NoSolution),
+        count(Status::Error),
+        count(Status::Timeout),
+        solve_time
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solution::Solution;
+
+    struct Lines;
+    struct Panics;
+
+    impl Solution for Lines {
+        const DAY: u32 = 98;
+        type Input = usize;
+        type Answer1 = usize;
+        type Answer2 = usize;
+
+        fn parse(content: &str) -> AdventResult<usize> {
+            Ok(content.lines().count())
+        }
+        fn part1(input: &usize) -> AdventResult<usize> {
+            Ok(*input)
+        }
+        fn part2(_: &usize) -> AdventResult<usize> {
+            Err(AdventError::NoSolution)
+        }
+        fn generate(_: &mut Rng, _: usize) -> String {
+            String::new()
+        }
+    }
+
+    impl Solution for Panics {
+        const DAY: u32 = 99;
+        type Input = ();
+        type Answer1 = u32;
+        type Answer2 = u32;
+
+        fn parse(_: &str) -> AdventResult<()> {
+            Ok(())
+        }
+        fn part1(_: &()) -> AdventResult<u32> {
+            panic!("boom")
+        }
+        fn part2(_: &()) -> AdventResult<u32> {
+            Ok(2)
+        }
+        fn generate(_: &mut Rng, _: usize) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"C:\aoc"), r#""C:\\aoc""#);
+        assert_eq!(json_string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(json_string("\u{1}\u{1f}"), r#""\u0001\u001f""#);
+        assert_eq!(json_string("é ✓"), "\"é ✓\"");
+    }
+
+    #[test]
+    fn result_json() {
+        let mut solved = PartResult::new(1, 2, Ok("42".to_owned()));
+        solved.parse_time = Duration::from_nanos(5);
+        solved.solve_time = Duration::from_nanos(7);
+        assert_eq!(
+            solved.to_json(),
+            r#"{"day":1,"part":2,"status":"solved","answer":"42","error":null,"parse_ns":5,"solve_ns":7}"#
+        );
+
+        let missing = PartResult::new(3, 1, Err(AdventError::NoSolution));
+        assert_eq!(
+            missing.to_json(),
+            r#"{"day":3,"part":1,"status":"no solution","answer":null,"error":null,"parse_ns":0,"solve_ns":0}"#
+        );
+
+        let failed = PartResult::new(4, 1, Err(AdventError::Solver("bad \"rule\"".to_owned())));
+        assert_eq!(
+            failed.to_json(),
+            r#"{"day":4,"part":1,"status":"error","answer":null,"error":"solver failed: bad \"rule\"","parse_ns":0,"solve_ns":0}"#
+        );
+
+        let late = PartResult::new(5, 2, Err(AdventError::Timeout(Duration::from_secs(1))));
+        assert!(late
+            .to_json()
+            .contains(r#""status":"timeout","answer":null,"error":"timed out after 1s""#));
+    }
+
+    #[test]
+    fn parallel_run_isolates_panics() {
+        let path = std::env::temp_dir().join(format!("aoc2020-report-{}.txt", std::process::id()));
+        std::fs::write(&path, "a\nb\nc\n").unwrap();
+        let path = path.to_str().unwrap().to_owned();
+
+        let jobs: Vec<(&dyn DynSolution, String)> = vec![
+            (&Panics, path.clone()),
+            (&Lines, path.clone()),
+            (&Panics, path.clone()),
+            (&Lines, path.clone()),
+        ];
+        let results = run_parallel(&jobs, PartSelection::Both, None, 3);
+        std::fs::remove_file(&path).unwrap();
+
+        let days: Vec<u32> = results.iter().map(|result| result.day).collect();
+        assert_eq!(days, vec![99, 99, 98, 98, 99, 99, 98, 98]);
+        assert_eq!(results[0].status, Status::Error);
+        assert_eq!(
+            results[0].error.as_deref(),
+            Some("solver failed: panicked: boom")
+        );
+        assert_eq!(results[2].answer.as_deref(), Some("3"));
+        assert_eq!(results[3].status, Status::NoSolution);
+
+        let table = to_table(&results);
+        assert!(table.contains("2 solved, 2 without solution, 4 errors, 0 timeouts"));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::common::*;
+use crate::solution;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Starting point of a new day; `__DAY__` stands for the day number.
+const TEMPLATE: &str = r#"use crate::common::*;
+use crate::solution::Solution;
+
+pub fn parse_input(content: &str) -> AdventResult<Vec<String>> {
+    Ok(content.lines().map(str::to_owned).collect())
+}
+
+/// `size` random records that `parse_input` accepts.
+pub(crate) fn generate_input(rng: &mut Rng, size: usize) -> String {
+    (0..size)
+        .map(|_| format!("{}\n", rng.range(0, 1000)))
+        .collect()
+}
+
+pub struct Day__DAY__;
+
+impl Solution for Day__DAY__ {
+    const DAY: u32 = __DAY__;
+
+    type Input = Vec<String>;
+    type Answer1 = usize;
+    type Answer2 = usize;
+
+    fn parse(content: &str) -> AdventResult<Self::Input> {
+        parse_input(content)
+    }
+
+    fn part1(input: &Self::Input) -> AdventResult<usize> {
+        // TODO: solve part 1.
+        Ok(input.len())
+    }
+
+    fn part2(input: &Self::Input) -> AdventResult<usize> {
+        // TODO: solve part 2.
+        Ok(input.len())
+    }
+
+    fn generate(rng: &mut Rng, size: usize) -> String {
+        generate_input(rng, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // TODO: paste the puzzle example and its answers.
+    const EXAMPLE: &str = "";
+    const EXAMPLE_ANSWERS: Option<(usize, usize)> = None;
+
+    #[test]
+    fn example() {
+        let (part1, part2) = EXAMPLE_ANSWERS.expect("fill in the example answers");
+        let input = parse_input(EXAMPLE).unwrap();
+        assert_eq!(Day__DAY__::part1(&input).unwrap(), part1);
+        assert_eq!(Day__DAY__::part2(&input).unwrap(), part2);
+    }
+}
+"#;
+
+const MAX_WIDTH: usize = 100;
+
+pub fn module_source(day: u32) -> String {
+    TEMPLATE.replace("__DAY__", &day.to_string())
+}
+
+fn not_found(message: &str) -> AdventError {
+    AdventError::parse(message, "")
+}
+
+/// `dayN` when `line` is `pub mod dayN;`.
+fn day_mod(line: &str) -> Option<&str> {
+    line.strip_prefix("pub mod ")
+        .and_then(|rest| rest.strip_suffix(';'))
+        .filter(|name| name.starts_with("day"))
+}
+
+/// Adds `pub mod dayN;` to the day modules of `lib.rs`, keeping the order
+/// rustfmt gives them.
+pub fn register_module(lib: &str, day: u32) -> AdventResult<String> {
+    let module = format!("day{}", day);
+    let line = format!("pub mod {};", module);
+    let lines: Vec<&str> = lib.lines().collect();
+    if lines.contains(&line.as_str()) {
+        return Ok(lib.to_owned());
+    }
+
+    let last = lines
+        .iter()
+        .rposition(|line| day_mod(line).is_some())
+        .ok_or_else(|| not_found("Expected 'pub mod dayN;' lines"))?;
+    let position = lines
+        .iter()
+        .position(|line| day_mod(line).is_some_and(|existing| existing > module.as_str()))
+        .unwrap_or(last + 1);
+
+    let mut out: Vec<&str> = lines[..position].to_vec();
+    out.push(&line);
+    out.extend(&lines[position..]);
+    Ok(out.join("\n") + "\n")
+}
+
+/// Adds `dayN` to the `use crate::{...}` list and `&dayN::DayN` to
+/// `SOLUTIONS` in `solution.rs`.
+pub fn register_solution(source: &str, day: u32) -> AdventResult<String> {
+    let module = format!("day{}", day);
+
+    let start = source
+        .find("use crate::{")
+        .ok_or_else(|| not_found("Expected a 'use crate::{...}' import"))?;
+    let end = start
+        + source[start..]
+            .find("};")
+            .ok_or_else(|| not_found("Unterminated 'use crate::{...}' import"))?
+        + 2;
+    let list = &source[start + "use crate::{".len()..end - 2];
+    let mut modules: Vec<&str> = list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    if !modules.contains(&module.as_str()) {
+        modules.push(&module);
+        modules.sort_unstable();
+    }
+    let import = render_import(&modules);
+
+    let registry = source
+        .find("pub static SOLUTIONS")
+        .ok_or_else(|| not_found("Expected the SOLUTIONS registry"))?;
+    let entry = format!("&{}::Day{},", module, day);
+    let mut out = String::with_capacity(source.len() + 64);
+    out.push_str(&source[..start]);
+    out.push_str(&import);
+    out.push_str(&source[end..registry]);
+
+    let registry_end = registry
+        + source[registry..]
+            .find("];")
+            .ok_or_else(|| not_found("Unterminated SOLUTIONS registry"))?;
+    let entries = &source[registry..registry_end];
+    if entries.lines().any(|line| line.trim() == entry) {
+        out.push_str(&source[registry..]);
+        return Ok(out);
+    }
+
+    // Entries are in day order; insert before the first later day.
+    let mut offset = 0;
+    let mut insert_at = entries.len();
+    for line in entries.split_inclusive('\n') {
+        let later = line
+            .trim()
+            .strip_prefix("&day")
+            .and_then(|rest| rest.split("::").next())
+            .and_then(|number| number.parse::<u32>().ok())
+            .is_some_and(|existing| existing > day);
+        if later {
+            insert_at = offset;
+            break;
+        }
+        offset += line.len();
+    }
+
+    out.push_str(&entries[..insert_at]);
+    out.push_str(&format!("    {}\n", entry));
+    out.push_str(&entries[insert_at..]);
+    out.push_str(&source[registry_end..]);
+    Ok(out)
+}
+
+/// `use crate::{...};` laid out the way rustfmt would.
+fn render_import(modules: &[&str]) -> String {
+    let single = format!("use crate::{{{}}};", modules.join(", "));
+    if single.len() <= MAX_WIDTH {
+        return single;
+    }
+
+    let mut out = String::from("use crate::{\n");
+    let mut line = String::new();
+    for module in modules {
+        if !line.is_empty() && 4 + line.len() + 1 + module.len() + 1 > MAX_WIDTH {
+            out.push_str(&format!("    {}\n", line));
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(module);
+        line.push(',');
+    }
+    out.push_str(&format!("    {}\n}};", line));
+    out
+}
+
+fn read(path: &Path) -> AdventResult<String> {
+    fs::read_to_string(path)
+        .map_err(|err| AdventError::from(err).in_file(&path.display().to_string()))
+}
+
+fn write(path: &Path, content: &str) -> AdventResult<()> {
+    fs::write(path, content)
+        .map_err(|err| AdventError::from(err).in_file(&path.display().to_string()))
+}
+
+/// Creates `src/dayN.rs` under `root`, registers it in `lib.rs` and
+/// `solution.rs` and creates an empty example input. Returns the paths it
+/// created or changed. Nothing is written if the module already exists or
+/// a file to update does not look as expected.
+pub fn scaffold(root: &Path, day: u32) -> AdventResult<Vec<String>> {
+    let module_path = root.join(format!("src/day{}.rs", day));
+    if module_path.exists() {
+        let err = io::Error::new(io::ErrorKind::AlreadyExists, "day module already exists");
+        return Err(AdventError::from(err).in_file(&module_path.display().to_string()));
+    }
+
+    let lib_path = root.join("src/lib.rs");
+    let lib = register_module(&read(&lib_path)?, day)
+        .map_err(|err| err.in_file(&lib_path.display().to_string()))?;
+    let solution_path = root.join("src/solution.rs");
+    let registry = register_solution(&read(&solution_path)?, day)
+        .map_err(|err| err.in_file(&solution_path.display().to_string()))?;
+
+    write(&module_path, &module_source(day))?;
+    write(&lib_path, &lib)?;
+    write(&solution_path, &registry)?;
+
+    let example_path = root.join(solution::input_path(day, "example"));
+    if let Some(dir) = example_path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| AdventError::from(err).in_file(&dir.display().to_string()))?;
+    }
+    if !example_path.exists() {
+        write(&example_path, "")?;
+    }
+
+    Ok([module_path, lib_path, solution_path, example_path]
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_module_in_rustfmt_order() {
+        let lib = register_module(include_str!("lib.rs"), 15).unwrap();
+        let mods: Vec<&str> = lib
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub mod day"))
+            .filter_map(|line| line.strip_suffix(';'))
+            .collect();
+        let mut sorted = mods.clone();
+        sorted.sort_unstable();
+        assert_eq!(mods, sorted);
+        assert!(mods.contains(&"15"));
+        assert_eq!(register_module(&lib, 15).unwrap(), lib);
+    }
+
+    #[test]
+    fn registers_solution() {
+        let source = register_solution(include_str!("solution.rs"), 15).unwrap();
+        assert!(source.contains("day14, day15, day2"));
+        assert!(source.contains("    &day14::Day14,\n    &day15::Day15,\n];"));
+        assert_eq!(register_solution(&source, 15).unwrap(), source);
+
+        let source = register_solution(include_str!("solution.rs"), 3).unwrap();
+        assert_eq!(source, include_str!("solution.rs"));
+    }
+
+    #[test]
+    fn template_names_the_day() {
+        let source = module_source(15);
+        assert!(source.contains("impl Solution for Day15"));
+        assert!(source.contains("const DAY: u32 = 15;"));
+        assert!(!source.contains("__DAY__"));
+        // Only the example test should fail until the day is solved.
+        assert!(!source.contains("NoSolution"));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::common::{AdventError, AdventResult, Rng};
+use crate::{
+    day1, day10, day11, day12, day13, day14, day2, day3, day4, day5, day6, day7, day8, day9,
+};
+use std::any::Any;
+use std::fmt::Display;
+
+/// A puzzle solver: parses the input text once, then answers both parts from it.
+///
+/// A part returns `AdventError::NoSolution` when the input has no answer for it.
+pub trait Solution {
+    const DAY: u32;
+
+    type Input: 'static;
+    type Answer1: Display;
+    type Answer2: Display;
+
+    fn parse(content: &str) -> AdventResult<Self::Input>;
+    fn part1(input: &Self::Input) -> AdventResult<Self::Answer1>;
+    fn part2(input: &Self::Input) -> AdventResult<Self::Answer2>;
+
+    /// A random input of about `size` records that `parse` accepts.
+    fn generate(rng: &mut Rng, size: usize) -> String;
+}
+
+/// Object-safe view of a `Solution`, so solvers with different input and
+/// answer types can live in the same registry.
+pub trait DynSolution: Sync {
+    fn day(&self) -> u32;
+    fn parse_input(&self, content: &str) -> AdventResult<Box<dyn Any>>;
+    fn solve_part1(&self, input: &dyn Any) -> AdventResult<String>;
+    fn solve_part2(&self, input: &dyn Any) -> AdventResult<String>;
+    fn generate_input(&self, rng: &mut Rng, size: usize) -> String;
+}
+
+impl<S: Solution + Sync> DynSolution for S {
+    fn day(&self) -> u32 {
+        S::DAY
+    }
+
+    fn parse_input(&self, content: &str) -> AdventResult<Box<dyn Any>> {
+        Ok(Box::new(S::parse(content)?))
+    }
+
+    fn solve_part1(&self, input: &dyn Any) -> AdventResult<String> {
+        S::part1(downcast_input::<S>(input)).map(|answer| answer.to_string())
+    }
+
+    fn solve_part2(&self, input: &dyn Any) -> AdventResult<String> {
+        S::part2(downcast_input::<S>(input)).map(|answer| answer.to_string())
+    }
+
+    fn generate_input(&self, rng: &mut Rng, size: usize) -> String {
+        S::generate(rng, size)
+    }
+}
+
+fn downcast_input<S: Solution>(input: &dyn Any) -> &S::Input {
+    input
+        .downcast_ref()
+        .expect("Input was not produced by this solution")
+}
+
+pub const DEFAULT_INPUT: &str = "input";
+/// Size of generated inputs when `generate` is not given one.
+pub const DEFAULT_GENERATE_SIZE: usize = 1000;
+
+/// Path of the `name` input of `day`, e.g. `inputs/day3/input.txt`.
+pub fn input_path(day: u32, name: &str) -> String {
+    format!("inputs/day{}/{}.txt", day, name)
+}
+
+pub static SOLUTIONS: &[&dyn DynSolution] = &[
+    &day1::Day1,
+    &day2::Day2,
+    &day3::Day3,
+    &day4::Day4,
+    &day5::Day5,
+    &day6::Day6,
+    &day7::Day7,
+    &day8::Day8,
+    &day9::Day9,
+    &day10::Day10,
+    &day11::Day11,
+    &day12::Day12,
+    &day13::Day13,
+    &day14::Day14,
+];
+
+pub fn find_solution(day: u32) -> AdventResult<&'static dyn DynSolution> {
+    SOLUTIONS
+        .iter()
+        .copied()
+        .find(|solution| solution.day() == day)
+        .ok_or(AdventError::UnknownDay(day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_inputs_are_solvable() {
+        for solution in SOLUTIONS {
+            for (seed, size) in (0..3).zip([100, 100, DEFAULT_GENERATE_SIZE]) {
+                let content = solution.generate_input(&mut Rng::new(seed), size);
+                let input = solution
+                    .parse_input(&content)
+                    .unwrap_or_else(|err| panic!("day{} seed {}: {}", solution.day(), seed, err));
+                let answers = [
+                    solution.solve_part1(input.as_ref()),
+                    solution.solve_part2(input.as_ref()),
+                ];
+                for answer in &answers {
+                    if let Err(err) = answer {
+                        panic!("day{} seed {}: {}", solution.day(), seed, err);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generators_are_deterministic() {
+        for solution in SOLUTIONS {
+            let first = solution.generate_input(&mut Rng::new(7), 50);
+            let second = solution.generate_input(&mut Rng::new(7), 50);
+            assert_eq!(first, second, "day{}", solution.day());
+        }
+    }
+
+    /// Small random edits that tend to break the input format.
+    fn mutate(rng: &mut Rng, content: &str) -> String {
+        const ALPHABET: [char; 18] = [
+            '0', '9', '-', '+', ' ', '\n', ':', ',', 'x', 'X', '#', '.', '[', ']', '=', 'a',
+            '\u{e9}', '\u{feff}',
+        ];
+        let mut chars: Vec<char> = content.chars().collect();
+        for _ in 0..rng.range(1, 8) {
+            let at = rng.index(chars.len() + 1);
+            match rng.range(0, 4) {
+                0 if at < chars.len() => {
+                    chars.remove(at);
+                }
+                1 if at < chars.len() => chars[at] = *rng.choose(&ALPHABET),
+                2 => chars.truncate(at),
+                _ => chars.insert(at, *rng.choose(&ALPHABET)),
+            }
+        }
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn parsers_reject_garbage_without_panicking() {
+        for solution in SOLUTIONS {
+            for seed in 0..300 {
+                let mut rng = Rng::new(seed);
+                let content = solution.generate_input(&mut rng, 5);
+                let content = mutate(&mut rng, &content);
+                let parsed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    solution.parse_input(&content).map(|_| ())
+                }));
+                assert!(
+                    parsed.is_ok(),
+                    "day{} panicked on {:?}",
+                    solution.day(),
+                    content
+                );
+            }
+        }
+    }
+}